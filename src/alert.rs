@@ -0,0 +1,81 @@
+use std::env;
+
+use serde::Deserialize;
+use teloxide::{
+    payloads::SendMessageSetters, requests::Requester, types::ChatId, types::ParseMode, Bot,
+};
+
+use crate::config::{AlertRoute, Config};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            Severity::Info => "ℹ️",
+            Severity::Warning => "⚠️",
+            Severity::Critical => "🚨",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AlertService {
+    bot: Bot,
+    routes: Vec<AlertRoute>,
+    silent_below: Severity,
+}
+
+impl AlertService {
+    /// Without configured routes every alert goes to `ADMIN_ID`.
+    pub fn new(bot: Bot, config: &Config) -> Self {
+        let alerts = config.alerts.clone();
+
+        let routes = if alerts.routes.is_empty() {
+            vec![AlertRoute {
+                chat_id: env::var("ADMIN_ID").unwrap().parse().unwrap(),
+                min_severity: Severity::Info,
+                max_severity: Severity::Critical,
+            }]
+        } else {
+            alerts.routes
+        };
+
+        AlertService {
+            bot,
+            routes,
+            silent_below: alerts.silent_below,
+        }
+    }
+
+    /// Sends a MarkdownV2 formatted alert to every route accepting its severity.
+    /// Failures are only logged, so a Telegram outage never stops the monitoring loops.
+    pub async fn send(&self, severity: Severity, text: String) {
+        let routes = self
+            .routes
+            .iter()
+            .filter(|route| route.min_severity <= severity && severity <= route.max_severity);
+
+        for route in routes {
+            if let Err(error) = self
+                .bot
+                .send_message(ChatId(route.chat_id), text.clone())
+                .parse_mode(ParseMode::MarkdownV2)
+                .disable_notification(severity < self.silent_below)
+                .await
+            {
+                log::error!(
+                    "Error in send alert to {}. message: {}",
+                    route.chat_id,
+                    error
+                );
+            }
+        }
+    }
+}
@@ -1,21 +1,70 @@
 use regex::Regex;
 use std::env;
 use std::error::Error;
+use std::sync::{Arc, Mutex};
 use teloxide::prelude::*;
 use tokio::sync::mpsc;
 
+use crate::alert::AlertService;
 use crate::bot_handler::BotHandler;
+use crate::config::Config;
+use crate::disk::DiskService;
+use crate::earnings::EarningsService;
+use crate::heartbeat::{HeartbeatService, HeartbeatWatcher, Heartbeats};
+use crate::history::StateHistory;
+use crate::miners::StatsCache;
+use crate::monitor::MonitorService;
+use crate::power::PowerService;
+use crate::probe::{ProbeResults, ProbeService};
+use crate::profit::ProfitSwitcher;
+use crate::report::ReportService;
+use crate::samples::SampleStore;
+use crate::thermal::{ThermalHold, ThermalService};
 
 pub struct TelegramBotService {
     bot: Bot,
+    config: Config,
     handler: BotHandler,
+    history: Arc<Mutex<StateHistory>>,
+    stats: StatsCache,
+    samples: Arc<Mutex<SampleStore>>,
+    earnings: EarningsService,
+    probes: ProbeResults,
+    heartbeats: Heartbeats,
+    thermal_hold: ThermalHold,
 }
 
 impl TelegramBotService {
-    pub fn new() -> Self {
+    /// Every service gets its settings from the one `config` loaded at startup.
+    pub fn new(config: Config) -> Self {
+        let history = Arc::new(Mutex::new(StateHistory::new()));
+        let stats = StatsCache::default();
+        let samples = Arc::new(Mutex::new(SampleStore::new()));
+        let earnings = EarningsService::new(&config);
+        let probes = ProbeResults::default();
+        let heartbeats = Heartbeats::default();
+        let thermal_hold = ThermalHold::default();
+
         TelegramBotService {
             bot: Bot::new(env::var("BOT_TOKEN").unwrap()),
-            handler: BotHandler::new(),
+            handler: BotHandler::new(
+                history.clone(),
+                stats.clone(),
+                samples.clone(),
+                earnings.clone(),
+                probes.clone(),
+                heartbeats.clone(),
+                thermal_hold.clone(),
+                &config,
+            ),
+            config,
+            history,
+            stats,
+            samples,
+            earnings,
+            probes,
+            heartbeats,
+            thermal_hold,
         }
     }
 
@@ -26,6 +75,24 @@ impl TelegramBotService {
                     .handler
                     .start_message_handler(&self.bot, &msg, false)
                     .await;
+            } else if text == "/report"
+                && msg.chat.id.0.to_string() == env::var("ADMIN_ID").unwrap()
+            {
+                let _ = &self.handler.report_message_handler(&self.bot, &msg).await;
+            } else if let Some(captures) = Regex::new(r"^/availability\s+(\S+)")
+                .unwrap()
+                .captures(text)
+            {
+                if msg.chat.id.0.to_string() == env::var("ADMIN_ID").unwrap() {
+                    let _ = &self
+                        .handler
+                        .availability_message_handler(
+                            &self.bot,
+                            &msg,
+                            captures.get(1).unwrap().as_str(),
+                        )
+                        .await;
+                }
             }
         }
         Ok(())
@@ -40,61 +107,165 @@ impl TelegramBotService {
                 if message.chat.id.0.to_string() == env::var("ADMIN_ID").unwrap() {
                     if let Some(captures) = Regex::new(r"^supervisor_(.*)_start")
                         .unwrap()
-                        .captures(&data)
+                        .captures(data)
                     {
                         let _ = &self
                             .handler
                             .supervisor_start_handler(
                                 &self.bot,
-                                &message,
+                                message,
                                 &q,
                                 captures.get(1).unwrap().as_str(),
                             )
                             .await;
-                    } else if let Some(captures) = Regex::new(r"^supervisor_(.*)_stop")
-                        .unwrap()
-                        .captures(&data)
+                    } else if let Some(captures) =
+                        Regex::new(r"^supervisor_(.*)_stop").unwrap().captures(data)
                     {
                         let _ = &self
                             .handler
                             .supervisor_stop_handler(
                                 &self.bot,
-                                &message,
+                                message,
                                 &q,
                                 captures.get(1).unwrap().as_str(),
                             )
                             .await;
                     } else if let Some(captures) =
-                        Regex::new(r"^supervisor_(.*)").unwrap().captures(&data)
+                        Regex::new(r"^supervisor_(.*)").unwrap().captures(data)
                     {
                         let _ = &self
                             .handler
                             .supervisor_manager_handler(
                                 &self.bot,
-                                &message,
+                                message,
+                                &q,
+                                captures.get(1).unwrap().as_str(),
+                            )
+                            .await;
+                    } else if let Some(captures) =
+                        Regex::new(r"^chart_(.*)_(24h|7d)$").unwrap().captures(data)
+                    {
+                        let _ = &self
+                            .handler
+                            .chart_handler(
+                                &self.bot,
+                                message,
+                                &q,
+                                captures.get(1).unwrap().as_str(),
+                                captures.get(2).unwrap().as_str(),
+                            )
+                            .await;
+                    } else if let Some(captures) =
+                        Regex::new(r"^exclusive_start_(.*)").unwrap().captures(data)
+                    {
+                        let _ = &self
+                            .handler
+                            .supervisor_exclusive_start_handler(
+                                &self.bot,
+                                message,
+                                &q,
+                                captures.get(1).unwrap().as_str(),
+                            )
+                            .await;
+                    } else if let Some(captures) =
+                        Regex::new(r"^pool_(.*)_use_(.*)$").unwrap().captures(data)
+                    {
+                        let _ = &self
+                            .handler
+                            .pool_switch_handler(
+                                &self.bot,
+                                message,
+                                &q,
+                                captures.get(1).unwrap().as_str(),
+                                captures.get(2).unwrap().as_str(),
+                            )
+                            .await;
+                    } else if let Some(captures) =
+                        Regex::new(r"^pools_(.*)").unwrap().captures(data)
+                    {
+                        let _ = &self
+                            .handler
+                            .pools_handler(
+                                &self.bot,
+                                message,
+                                &q,
+                                captures.get(1).unwrap().as_str(),
+                            )
+                            .await;
+                    } else if let Some(captures) =
+                        Regex::new(r"^maintenance_run_(.*)").unwrap().captures(data)
+                    {
+                        let _ = &self
+                            .handler
+                            .maintenance_run_handler(
+                                &self.bot,
+                                message,
+                                &q,
+                                captures.get(1).unwrap().as_str(),
+                            )
+                            .await;
+                    } else if let Some(captures) = Regex::new(r"^plug_(.*)_(off|on|cycle)_confirm$")
+                        .unwrap()
+                        .captures(data)
+                    {
+                        let _ = &self
+                            .handler
+                            .plug_action_handler(
+                                &self.bot,
+                                message,
+                                &q,
+                                captures.get(1).unwrap().as_str(),
+                                captures.get(2).unwrap().as_str(),
+                            )
+                            .await;
+                    } else if let Some(captures) = Regex::new(r"^plug_(.*)_(off|on|cycle)$")
+                        .unwrap()
+                        .captures(data)
+                    {
+                        let _ = &self
+                            .handler
+                            .plug_confirm_handler(
+                                &self.bot,
+                                message,
                                 &q,
                                 captures.get(1).unwrap().as_str(),
+                                captures.get(2).unwrap().as_str(),
                             )
                             .await;
-                    } else if &data.as_str() == &"start_supervisors" {
+                    } else if let Some(captures) = Regex::new(r"^plug_(.*)").unwrap().captures(data)
+                    {
+                        let _ = &self
+                            .handler
+                            .plug_handler(&self.bot, message, &q, captures.get(1).unwrap().as_str())
+                            .await;
+                    } else if data.as_str() == "plugs" {
+                        let _ = &self.handler.plugs_handler(&self.bot, message, &q).await;
+                    } else if data.as_str() == "inventory" {
+                        let _ = &self.handler.inventory_handler(&self.bot, message, &q).await;
+                    } else if data.as_str() == "maintenance" {
                         let _ = &self
                             .handler
-                            .supervisor_start_all_handler(&self.bot, &message, &q)
+                            .maintenance_handler(&self.bot, message, &q)
                             .await;
-                    } else if &data.as_str() == &"stop_supervisors" {
+                    } else if data.as_str() == "start_supervisors" {
                         let _ = &self
                             .handler
-                            .supervisor_stop_all_handler(&self.bot, &message, &q)
+                            .supervisor_start_all_handler(&self.bot, message, &q)
                             .await;
-                    } else if &data.as_str() == &"reload_supervisors" {
+                    } else if data.as_str() == "stop_supervisors" {
                         let _ = &self
                             .handler
-                            .supervisor_reload_handler(&self.bot, &message, &q)
+                            .supervisor_stop_all_handler(&self.bot, message, &q)
                             .await;
-                    } else if &data.as_str() == &"back_to_home" {
+                    } else if data.as_str() == "reload_supervisors" {
                         let _ = &self
                             .handler
-                            .start_message_handler(&self.bot, &message, true)
+                            .supervisor_reload_handler(&self.bot, message, &q)
+                            .await;
+                    } else if data.as_str() == "back_to_home" {
+                        let _ = &self
+                            .handler
+                            .start_message_handler(&self.bot, message, true)
                             .await;
                     }
                 }
@@ -106,6 +277,79 @@ impl TelegramBotService {
     pub async fn initialize(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
         let (tx, mut rx) = mpsc::channel(100);
 
+        let alert_service = AlertService::new(self.bot.clone(), &self.config);
+
+        let mut monitor_service = MonitorService::new(
+            self.history.clone(),
+            self.stats.clone(),
+            self.samples.clone(),
+            alert_service.clone(),
+            &self.config,
+            self.thermal_hold.clone(),
+        );
+        tokio::spawn(async move {
+            monitor_service.run().await;
+        });
+
+        let profit_switcher = ProfitSwitcher::new(
+            self.samples.clone(),
+            self.history.clone(),
+            alert_service.clone(),
+            &self.config,
+            self.thermal_hold.clone(),
+        );
+        tokio::spawn(async move {
+            profit_switcher.run().await;
+        });
+
+        let earnings = self.earnings.clone();
+        tokio::spawn(async move {
+            earnings.run().await;
+        });
+
+        let mut thermal_service = ThermalService::new(
+            alert_service.clone(),
+            self.history.clone(),
+            &self.config,
+            self.thermal_hold.clone(),
+        );
+        tokio::spawn(async move {
+            thermal_service.run().await;
+        });
+
+        let mut disk_service = DiskService::new(alert_service.clone());
+        tokio::spawn(async move {
+            disk_service.run().await;
+        });
+
+        let mut probe_service =
+            ProbeService::new(self.probes.clone(), alert_service.clone(), &self.config);
+        tokio::spawn(async move {
+            probe_service.run().await;
+        });
+
+        let heartbeat_service = HeartbeatService::new();
+        tokio::spawn(async move {
+            heartbeat_service.run().await;
+        });
+
+        let mut heartbeat_watcher =
+            HeartbeatWatcher::new(self.heartbeats.clone(), alert_service.clone(), &self.config);
+        tokio::spawn(async move {
+            heartbeat_watcher.run().await;
+        });
+
+        let report_service = ReportService::new(
+            self.history.clone(),
+            self.samples.clone(),
+            self.earnings.clone(),
+            PowerService::new(&self.config),
+        );
+        let report_bot = self.bot.clone();
+        tokio::spawn(async move {
+            report_service.run_schedule(&report_bot).await;
+        });
+
         let bot_clone = self.bot.clone();
         let tx_clone = tx.clone();
 
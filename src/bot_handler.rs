@@ -1,20 +1,49 @@
 use teloxide::{
-    payloads::{AnswerCallbackQuerySetters, EditMessageTextSetters, SendMessageSetters},
+    payloads::{AnswerCallbackQuerySetters, EditMessageMediaSetters, EditMessageTextSetters, SendMessageSetters, SendPhotoSetters},
     requests::Requester,
-    types::{CallbackQuery, InlineKeyboardButton, InlineKeyboardMarkup, Message, ParseMode},
+    types::{CallbackQuery, InlineKeyboardButton, InlineKeyboardMarkup, InputFile, InputMedia, InputMediaPhoto, Message, ParseMode},
     Bot,
 };
 
-use crate::{supervisor::{SupervisorService, Process}, utils::markdown};
+use std::sync::{Arc, Mutex};
+
+use crate::{chart, config::Config, earnings::EarningsService, history::{StateHistory, AVAILABILITY_WINDOWS}, heartbeat::{self, Heartbeats}, host::{HostMetrics, ProcessMetrics}, inventory::Inventory, maintenance::MaintenanceService, miners::{self, StatsCache}, plug::{PlugAction, PlugService}, pools::PoolSwitcher, power::PowerService, probe::ProbeResults, report::ReportService, samples::{Sample, SampleStore}, supervisor::{SupervisorService, Process}, thermal::ThermalHold, utils::{markdown, timedate}};
 
 pub struct BotHandler {
     supervisor_service: SupervisorService,
+    report_service: ReportService,
+    history: Arc<Mutex<StateHistory>>,
+    stats: StatsCache,
+    samples: Arc<Mutex<SampleStore>>,
+    pool_switcher: PoolSwitcher,
+    config: Config,
+    earnings: EarningsService,
+    power: PowerService,
+    maintenance: MaintenanceService,
+    plugs: PlugService,
+    probes: ProbeResults,
+    heartbeats: Heartbeats,
+    thermal_hold: ThermalHold,
 }
 
 impl BotHandler {
-    pub fn new() -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(history: Arc<Mutex<StateHistory>>, stats: StatsCache, samples: Arc<Mutex<SampleStore>>, earnings: EarningsService, probes: ProbeResults, heartbeats: Heartbeats, thermal_hold: ThermalHold, config: &Config) -> Self {
         BotHandler {
             supervisor_service: SupervisorService::new(),
+            report_service: ReportService::new(history.clone(), samples.clone(), earnings.clone(), PowerService::new(config)),
+            pool_switcher: PoolSwitcher::new(history.clone(), thermal_hold.clone(), config),
+            history,
+            stats,
+            samples,
+            config: config.clone(),
+            earnings,
+            power: PowerService::new(config),
+            maintenance: MaintenanceService::new(config),
+            plugs: PlugService::new(config),
+            probes,
+            heartbeats,
+            thermal_hold,
         }
     }
 
@@ -25,7 +54,7 @@ impl BotHandler {
             .unwrap()
     }
 
-    fn create_supervisor_keyboard(&self, process_list: &Vec<Process>) -> InlineKeyboardMarkup {
+    fn create_supervisor_keyboard(&self, process_list: &[Process]) -> InlineKeyboardMarkup {
         let mut keyboard = vec![vec![InlineKeyboardButton::callback(
             "Supervisors 👇".to_owned(),
             "-".to_owned(),
@@ -63,11 +92,248 @@ impl BotHandler {
             InlineKeyboardButton::callback("Reload supervisor 🔄", "reload_supervisors"),
         ]
         ]);
+        let mut tools = vec![InlineKeyboardButton::callback("🧰 Inventory", "inventory")];
+        if !self.maintenance.commands().is_empty() {
+            tools.push(InlineKeyboardButton::callback("🛠 Maintenance", "maintenance"));
+        }
+        if !self.plugs.rigs().is_empty() {
+            tools.push(InlineKeyboardButton::callback("🔌 Power", "plugs"));
+        }
+        keyboard.push(tools);
 
         InlineKeyboardMarkup::new(keyboard)
     }
 
-    fn format_supervisor_status(&self, process_list: &Vec<Process>) -> String {
+    fn format_availability(&self, program_name: &str) -> String {
+        let now = chrono::Utc::now().timestamp();
+        self.history
+            .lock()
+            .unwrap()
+            .availability(program_name, now)
+            .iter()
+            .map(|(label, percent)| match percent {
+                Some(percent) => format!("{} {:.1}%", label, percent),
+                None => format!("{} unknown", label),
+            })
+            .collect::<Vec<String>>()
+            .join(" | ")
+    }
+
+    fn format_miner_stats(&self, program_name: &str) -> String {
+        let stats = match miners::fresh_stats(&self.stats, program_name) {
+            Some(stats) => stats,
+            None => return String::new(),
+        };
+
+        let format_optional = |hashrate: Option<f64>| match hashrate {
+            Some(hashrate) => miners::format_hashrate(hashrate),
+            None => "n/a".to_string(),
+        };
+
+        let mut lines = vec![format!(
+            "*hashrate*: {}",
+            markdown::replace_specail_chars(&format!(
+                "10s {} | 60s {} | 15m {}",
+                format_optional(stats.hashrate.short),
+                format_optional(stats.hashrate.medium),
+                format_optional(stats.hashrate.long)
+            ))
+        )];
+
+        if let (Some(accepted), Some(rejected)) = (stats.accepted_shares, stats.rejected_shares) {
+            match stats.stale_shares {
+                Some(stale) => lines.push(format!("*shares*: {} accepted / {} rejected / {} stale", accepted, rejected, stale)),
+                None => lines.push(format!("*shares*: {} accepted / {} rejected", accepted, rejected)),
+            }
+        }
+        if let Some(hardware_errors) = stats.hardware_errors {
+            lines.push(format!("*hardware errors*: {}", hardware_errors));
+        }
+        if let Some(pool) = &stats.pool {
+            lines.push(format!("*pool*: {}", markdown::replace_specail_chars(pool)));
+        }
+        if let Some(difficulty) = stats.difficulty {
+            lines.push(format!("*difficulty*: {}", difficulty));
+        }
+        if let Some(version) = &stats.version {
+            lines.push(format!("*version*: {}", markdown::replace_specail_chars(version)));
+        }
+
+        if !stats.devices.is_empty() {
+            lines.push("\n*devices*:".to_string());
+            lines.extend(stats.devices.iter().map(|device| {
+                let mut details = vec![];
+                if let Some(hashrate) = device.hashrate {
+                    details.push(miners::format_hashrate(hashrate));
+                }
+                if let Some(temperature) = device.temperature {
+                    details.push(format!("{:.0}°C", temperature));
+                }
+                if let Some(hardware_errors) = device.hardware_errors {
+                    details.push(format!("HW {}", hardware_errors));
+                }
+                if let Some(status) = &device.status {
+                    details.push(status.clone());
+                }
+                markdown::replace_specail_chars(&format!("• {}: {}", device.name, details.join(", ")))
+            }));
+        }
+
+        if !stats.pools.is_empty() {
+            lines.push("\n*pools*:".to_string());
+            lines.extend(stats.pools.iter().map(|pool| {
+                let shares = match (pool.accepted, pool.rejected) {
+                    (Some(accepted), Some(rejected)) => format!(", {} accepted / {} rejected", accepted, rejected),
+                    _ => String::new(),
+                };
+                markdown::replace_specail_chars(&format!(
+                    "• {}: {}{}{}",
+                    pool.url,
+                    pool.status,
+                    if pool.active { " (active)" } else { "" },
+                    shares
+                ))
+            }));
+        }
+
+        format!("\n\n{}", lines.join("\n"))
+    }
+
+    fn format_reject_rates(&self, program_name: &str) -> String {
+        let now = chrono::Utc::now().timestamp();
+        let samples = self.samples.lock().unwrap();
+
+        let rates = [("1h", 3600), ("24h", 86400)]
+            .iter()
+            .filter_map(|(label, secs)| {
+                let counts = samples.share_counts(program_name, now - secs, now)?;
+                let rate = counts.reject_rate()?;
+                Some(format!("{} {:.2}% of {}", label, rate, counts.total()))
+            })
+            .collect::<Vec<String>>();
+
+        if rates.is_empty() {
+            String::new()
+        } else {
+            format!("\n*reject rate*: {}", markdown::replace_specail_chars(&rates.join(" | ")))
+        }
+    }
+
+    fn format_pool_probes(&self, program_name: &str) -> String {
+        let probes = self.probes.lock().unwrap();
+        let results = match probes.get(program_name) {
+            Some(results) if !results.is_empty() => results,
+            _ => return String::new(),
+        };
+
+        let lines = results
+            .iter()
+            .map(|result| markdown::replace_specail_chars(&format!("• {}", result.format())))
+            .collect::<Vec<String>>();
+        let checked_at = results.iter().map(|result| result.checked_at).max().unwrap_or_default();
+        let checked = timedate::diff_for_humans(checked_at, chrono::Utc::now().timestamp());
+        format!("\n\n*pool probes* \\(checked {}\\):\n{}", markdown::replace_specail_chars(&checked), lines.join("\n"))
+    }
+
+    fn format_earnings(&self, program_name: &str) -> String {
+        let now = chrono::Utc::now().timestamp();
+        let history = self.history.lock().unwrap();
+        let samples = self.samples.lock().unwrap();
+
+        match self.earnings.estimate(&history, &samples, program_name, now - 86400, now) {
+            Some(estimate) => format!("\n*earnings*: {}", markdown::replace_specail_chars(&self.earnings.format(&estimate))),
+            None => String::new(),
+        }
+    }
+
+    fn format_rig_earnings(&self, process_list: &[Process]) -> String {
+        let now = chrono::Utc::now().timestamp();
+        let history = self.history.lock().unwrap();
+        let samples = self.samples.lock().unwrap();
+        let program_names: Vec<String> = process_list.iter().map(|program| program.name.clone()).collect();
+
+        match self.earnings.total_value(&history, &samples, &program_names, now - 86400, now) {
+            Some(total) => format!(
+                "\n\n*rig earnings*: {}",
+                markdown::replace_specail_chars(&format!("≈ {:.2} {}/day", total, self.earnings.currency()))
+            ),
+            None => String::new(),
+        }
+    }
+
+    fn format_power(&self, program_name: &str) -> String {
+        let now = chrono::Utc::now().timestamp();
+        let history = self.history.lock().unwrap();
+        let samples = self.samples.lock().unwrap();
+
+        let Some(usage) = self.power.usage(&history, program_name, now - 86400, now) else {
+            return String::new();
+        };
+        let mut text = format!("\n*power \\(24h\\)*: {}", markdown::replace_specail_chars(&self.power.format(&usage)));
+
+        let net = self
+            .earnings
+            .estimate(&history, &samples, program_name, now - 86400, now)
+            .and_then(|estimate| estimate.value_per_day)
+            .and_then(|value| self.power.net_per_day(value, self.earnings.currency(), &usage, now - 86400, now));
+        if let Some(net) = net {
+            text += &format!("\n*net*: {}", markdown::replace_specail_chars(&self.power.format_net(net)));
+        }
+        text
+    }
+
+    fn format_rig_power(&self, process_list: &[Process]) -> String {
+        let now = chrono::Utc::now().timestamp();
+        let history = self.history.lock().unwrap();
+        let samples = self.samples.lock().unwrap();
+        let program_names: Vec<String> = process_list.iter().map(|program| program.name.clone()).collect();
+
+        let Some(usage) = self.power.rig_usage(&history, &program_names, now - 86400, now) else {
+            return String::new();
+        };
+        let mut text = format!("\n\n*rig power \\(24h\\)*: {}", markdown::replace_specail_chars(&self.power.format(&usage)));
+
+        let net = self
+            .earnings
+            .total_value(&history, &samples, &program_names, now - 86400, now)
+            .and_then(|value| self.power.net_per_day(value, self.earnings.currency(), &usage, now - 86400, now));
+        if let Some(net) = net {
+            text += &format!("\n*rig net*: {}", markdown::replace_specail_chars(&self.power.format_net(net)));
+        }
+        text
+    }
+
+    async fn format_process_metrics(&self, program: &Process) -> String {
+        if program.state != "RUNNING" {
+            return String::new();
+        }
+
+        let pid = program.pid;
+        match tokio::task::spawn_blocking(move || ProcessMetrics::read(pid)).await.unwrap_or_default() {
+            Some(metrics) => format!("\n*process*: {}", markdown::replace_specail_chars(&metrics.format())),
+            None => String::new(),
+        }
+    }
+
+    async fn format_host_metrics(&self) -> String {
+        let lines = tokio::task::spawn_blocking(HostMetrics::read)
+            .await
+            .map(|metrics| metrics.format())
+            .unwrap_or_default();
+
+        if lines.is_empty() {
+            return String::new();
+        }
+
+        let lines = lines
+            .iter()
+            .map(|(label, value)| format!("*{}*: {}", label, markdown::replace_specail_chars(value)))
+            .collect::<Vec<String>>();
+        format!("🖥 *Host*\n{}\n\n", lines.join("\n"))
+    }
+
+    async fn format_supervisor_status(&self, process_list: &[Process]) -> String {
+        let host_metrics = self.format_host_metrics().await;
         let supervisor_programs = process_list
             .iter()
             .map(|program| {
@@ -77,22 +343,70 @@ impl BotHandler {
                     "❌"
                 };
 
+                let hashrate = match miners::fresh_stats(&self.stats, &program.name).and_then(|stats| stats.hashrate.current()) {
+                    Some(hashrate) => format!("\n*hashrate*: {}", markdown::replace_specail_chars(&miners::format_hashrate(hashrate))),
+                    None => String::new(),
+                };
+
                 format!(
-                    "*name*: {}\n*status*: *{}* {}",
+                    "*name*: {}\n*status*: *{}* {}{}{}",
                     markdown::replace_specail_chars(&program.name),
                     &program.state,
-                    state_emoji
+                    state_emoji,
+                    hashrate,
+                    self.format_earnings(&program.name)
                 )
             })
             .collect::<Vec<String>>()
-            .join(&markdown::replace_specail_chars(&"\n---------------------------------\n".to_string()));
+            .join(&markdown::replace_specail_chars("\n---------------------------------\n"));
 
         format!(
-            "You can see a summary of the supervisor's status:\n\n\n{}\n\n\\.",
-            &supervisor_programs
+            "{}You can see a summary of the supervisor's status:\n\n\n{}{}{}\n\n\\.",
+            host_metrics,
+            &supervisor_programs,
+            self.format_rig_earnings(process_list),
+            self.format_rig_power(process_list)
         )
     }
 
+    /// Active members of the program's exclusion group, which have to stop before it starts.
+    fn conflicting_programs(&self, process_list: &[Process], program_name: &str) -> Vec<String> {
+        let group = match self.config.exclusion_group(program_name) {
+            Some(group) => group,
+            None => return vec![],
+        };
+
+        process_list
+            .iter()
+            .filter(|program| program.name != program_name && group.programs.contains(&program.name))
+            .filter(|program| is_active(program))
+            .map(|program| program.name.clone())
+            .collect()
+    }
+
+    /// Whether the program was stopped for overheating and must stay stopped.
+    fn is_held(&self, program_name: &str) -> bool {
+        self.thermal_hold.lock().unwrap().contains(program_name)
+    }
+
+    /// Stopped programs "Start all programs" should start: every program outside
+    /// exclusion groups, and the default of each group none of whose members runs.
+    /// Programs stopped for overheating are left out.
+    fn programs_to_start_all(&self, process_list: &[Process]) -> Vec<String> {
+        process_list
+            .iter()
+            .filter(|program| !is_active(program) && !self.is_held(&program.name))
+            .filter(|program| match self.config.exclusion_group(&program.name) {
+                Some(group) => {
+                    group.default_program() == Some(&program.name)
+                        && !process_list.iter().any(|member| group.programs.contains(&member.name) && is_active(member))
+                }
+                None => true,
+            })
+            .map(|program| program.name.clone())
+            .collect()
+    }
+
     async fn update_supervisor_message(&self, bot: &Bot, msg: &Message, text: String, keyboard: InlineKeyboardMarkup) -> Result<(), teloxide::RequestError> {
         bot.edit_message_text(msg.chat.id, msg.id, text)
             .parse_mode(ParseMode::MarkdownV2)
@@ -103,8 +417,8 @@ impl BotHandler {
 
     pub async fn start_message_handler(&self, bot: &Bot, msg: &Message, is_back: bool) -> Result<(), teloxide::RequestError> {
         let process_list = &self.get_supervisor_process_list().await;
-        let text = self.format_supervisor_status(&process_list);
-        let keyboard = self.create_supervisor_keyboard(&process_list);
+        let text = self.format_supervisor_status(process_list).await;
+        let keyboard = self.create_supervisor_keyboard(process_list);
 
         if is_back {
             self.update_supervisor_message(bot, msg, text, keyboard).await?;
@@ -117,6 +431,113 @@ impl BotHandler {
         Ok(())
     }
 
+    pub async fn report_message_handler(&self, bot: &Bot, msg: &Message) -> Result<(), teloxide::RequestError> {
+        let text = self.report_service.build_report(chrono::Duration::days(1)).await;
+
+        bot.send_message(msg.chat.id, text)
+            .parse_mode(ParseMode::MarkdownV2)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn availability_message_handler(&self, bot: &Bot, msg: &Message, supervisor_name: &str) -> Result<(), teloxide::RequestError> {
+        let now = chrono::Utc::now().timestamp();
+        let text = {
+            let history = self.history.lock().unwrap();
+            let windows = AVAILABILITY_WINDOWS
+                .iter()
+                .map(|(label, secs)| {
+                    let uptime = match history.uptime_percent(supervisor_name, now - secs, now) {
+                        Some(percent) => format!("{:.2}%", percent),
+                        None => "unknown".to_string(),
+                    };
+                    format!(
+                        "*{}*: {} \\({} crashes\\)",
+                        label,
+                        markdown::replace_specail_chars(&uptime),
+                        history.crash_count(supervisor_name, now - secs, now)
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join("\n");
+
+            format!(
+                "Availability of *{}*:\n\n{}\n\n\\.",
+                markdown::replace_specail_chars(supervisor_name),
+                windows
+            )
+        };
+
+        bot.send_message(msg.chat.id, text)
+            .parse_mode(ParseMode::MarkdownV2)
+            .await?;
+        Ok(())
+    }
+
+    /// Sends the hashrate chart of a program as a photo, or replaces the photo
+    /// when the window is switched from an already sent chart.
+    pub async fn chart_handler(&self, bot: &Bot, msg: &Message, query: &CallbackQuery, supervisor_name: &str, window: &str) -> Result<(), teloxide::RequestError> {
+        let now = chrono::Utc::now().timestamp();
+        let from = now - if window == "7d" { 7 * 86400 } else { 86400 };
+
+        // Locked in the same order as everywhere else, and released before
+        // rendering.
+        let (series, downtime, caption) = {
+            let history = self.history.lock().unwrap();
+            let samples = self.samples.lock().unwrap();
+            let series: Vec<Sample> = samples.series(supervisor_name, from, now).into_iter().cloned().collect();
+            let downtime = history.downtime(supervisor_name, from, now);
+
+            let mut caption = format!("📈 *{}* hashrate, last {}", markdown::replace_specail_chars(supervisor_name), window);
+            if let Some(average) = samples.average_hashrate(supervisor_name, from, now) {
+                caption.push_str(&format!("\naverage: {}", markdown::replace_specail_chars(&miners::format_hashrate(average))));
+            }
+            if let Some(uptime) = history.uptime_percent(supervisor_name, from, now) {
+                caption.push_str(&format!("\nuptime: {}", markdown::replace_specail_chars(&format!("{:.1}%", uptime))));
+            }
+
+            (series, downtime, caption)
+        };
+        let series: Vec<&Sample> = series.iter().collect();
+        let chart = chart::render_hashrate_chart(&series, &downtime, from, now);
+
+        let chart = match chart {
+            Ok(chart) => chart,
+            Err(error) => {
+                log::error!("Error in render chart of {}. message: {}", supervisor_name, error);
+                bot.answer_callback_query(&query.id)
+                    .text("Error in render chart.")
+                    .show_alert(true)
+                    .await?;
+                return Ok(());
+            }
+        };
+
+        let keyboard = InlineKeyboardMarkup::new(vec![vec![
+            InlineKeyboardButton::callback("24h".to_owned(), format!("chart_{}_24h", supervisor_name)),
+            InlineKeyboardButton::callback("7d".to_owned(), format!("chart_{}_7d", supervisor_name)),
+        ]]);
+        let photo = InputFile::memory(chart).file_name("chart.png");
+
+        if msg.photo().is_some() {
+            let media = InputMediaPhoto::new(photo)
+                .caption(caption)
+                .parse_mode(ParseMode::MarkdownV2);
+            bot.edit_message_media(msg.chat.id, msg.id, InputMedia::Photo(media))
+                .reply_markup(keyboard)
+                .await?;
+        } else {
+            bot.send_photo(msg.chat.id, photo)
+                .caption(caption)
+                .parse_mode(ParseMode::MarkdownV2)
+                .reply_markup(keyboard)
+                .await?;
+        }
+
+        bot.answer_callback_query(&query.id).await?;
+        Ok(())
+    }
+
     async fn handle_supervisor_action(&self, bot: &Bot, msg: &Message, query: &CallbackQuery, supervisor_name: &str, action: &str) -> Result<(), teloxide::RequestError> {
         
         let supervisor_name_ref = supervisor_name.to_string();
@@ -151,20 +572,31 @@ impl BotHandler {
                 "❌"
             };
 
-            let keyboard = vec![
+            let mut keyboard = vec![
                 vec![
                     InlineKeyboardButton::callback("Start".to_owned(), format!("supervisor_{}_start", &program.name)),
                     InlineKeyboardButton::callback("Stop".to_owned(), format!("supervisor_{}_stop", &program.name)),
                 ],
-                vec![InlineKeyboardButton::callback("Back 🔙".to_owned(), "back_to_home")],
+                vec![InlineKeyboardButton::callback("📈 Chart".to_owned(), format!("chart_{}_24h", &program.name))],
             ];
+            if !self.pool_switcher.profiles(&program.name).is_empty() {
+                keyboard.push(vec![InlineKeyboardButton::callback("🔀 Switch pool".to_owned(), format!("pools_{}", &program.name))]);
+            }
+            keyboard.push(vec![InlineKeyboardButton::callback("Back 🔙".to_owned(), "back_to_home")]);
 
             let text = format!(
-                "*name*: {}\n*status*: *{}* {}\nuptime: {}\n\n\\.",
+                "*name*: {}\n*status*: *{}* {}\nuptime: {}\navailability: {}{}{}{}{}{}{}\n\n\\.",
                 markdown::replace_specail_chars(&program.name),
                 &program.state,
                 state_emoji,
-                markdown::replace_specail_chars(&program.uptime)
+                markdown::replace_specail_chars(&program.uptime),
+                markdown::replace_specail_chars(&self.format_availability(&program.name)),
+                self.format_process_metrics(program).await,
+                self.format_miner_stats(&program.name),
+                self.format_reject_rates(&program.name),
+                self.format_pool_probes(&program.name),
+                self.format_earnings(&program.name),
+                self.format_power(&program.name)
             );
 
             self.update_supervisor_message(bot, msg, text, InlineKeyboardMarkup::new(keyboard)).await?;
@@ -185,11 +617,379 @@ impl BotHandler {
         Ok(())
     }
 
+    pub async fn pools_handler(&self, bot: &Bot, msg: &Message, query: &CallbackQuery, supervisor_name: &str) -> Result<(), teloxide::RequestError> {
+        let current_profile = self.pool_switcher.current_profile(supervisor_name);
+
+        let mut keyboard = self
+            .pool_switcher
+            .profiles(supervisor_name)
+            .into_iter()
+            .map(|profile| {
+                let label = if current_profile.as_ref() == Some(&profile) {
+                    format!("{} ✅", profile)
+                } else {
+                    profile.clone()
+                };
+                vec![InlineKeyboardButton::callback(label, format!("pool_{}_use_{}", supervisor_name, profile))]
+            })
+            .collect::<Vec<Vec<InlineKeyboardButton>>>();
+        keyboard.push(vec![InlineKeyboardButton::callback("Back 🔙".to_owned(), format!("supervisor_{}", supervisor_name))]);
+
+        let text = format!(
+            "Pool of *{}*: {}\nChoose the profile to switch to\\.",
+            markdown::replace_specail_chars(supervisor_name),
+            markdown::replace_specail_chars(current_profile.as_deref().unwrap_or("unknown"))
+        );
+
+        self.update_supervisor_message(bot, msg, text, InlineKeyboardMarkup::new(keyboard)).await?;
+        bot.answer_callback_query(&query.id).await?;
+        Ok(())
+    }
+
+    pub async fn pool_switch_handler(&self, bot: &Bot, msg: &Message, query: &CallbackQuery, supervisor_name: &str, profile: &str) -> Result<(), teloxide::RequestError> {
+        bot.answer_callback_query(&query.id)
+            .text(format!("Switching {} to {}, this takes a moment.", supervisor_name, profile))
+            .await?;
+
+        let keyboard = InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback("Back 🔙".to_owned(), format!("supervisor_{}", supervisor_name))]]);
+        let text = format!(
+            "⏳ Switching *{}* to *{}*\\.\\.\\.",
+            markdown::replace_specail_chars(supervisor_name),
+            markdown::replace_specail_chars(profile)
+        );
+        self.update_supervisor_message(bot, msg, text, keyboard.clone()).await?;
+
+        // The miner is given time to settle after the restart, so the switch
+        // runs in the background and the message is updated once it's done.
+        let bot = bot.clone();
+        let msg = msg.clone();
+        let pool_switcher = self.pool_switcher.clone();
+        let program = supervisor_name.to_string();
+        let profile_name = profile.to_string();
+        tokio::spawn(async move {
+            let result = {
+                let pool_switcher = pool_switcher.clone();
+                let program = program.clone();
+                let profile_name = profile_name.clone();
+                tokio::task::spawn_blocking(move || pool_switcher.switch(&program, &profile_name).map_err(|error| error.to_string()))
+                    .await
+                    .unwrap_or_else(|error| Err(error.to_string()))
+            };
+
+            let text = match result {
+                Ok(_) => format!(
+                    "✅ *{}* now mines on *{}*\\.",
+                    markdown::replace_specail_chars(&program),
+                    markdown::replace_specail_chars(&profile_name)
+                ),
+                Err(error) => {
+                    log::error!("Error in switch pool of {} to {}. message: {}", program, profile_name, error);
+                    format!("❌ Switching pool failed: {}", markdown::replace_specail_chars(&error))
+                }
+            };
+
+            let result = bot
+                .edit_message_text(msg.chat.id, msg.id, text)
+                .parse_mode(ParseMode::MarkdownV2)
+                .reply_markup(keyboard)
+                .await;
+            if let Err(error) = result {
+                log::error!("Error in report pool switch of {}. message: {}", program, error);
+            }
+        });
+        Ok(())
+    }
+
+    pub async fn inventory_handler(&self, bot: &Bot, msg: &Message, query: &CallbackQuery) -> Result<(), teloxide::RequestError> {
+        let inventory = tokio::task::spawn_blocking(Inventory::read).await.unwrap_or_default();
+
+        let format_readiness = |issues: &[String]| {
+            if issues.is_empty() {
+                "ready ✅".to_string()
+            } else {
+                let issues = issues.iter().map(|issue| format!("\n• {}", markdown::replace_specail_chars(issue))).collect::<String>();
+                format!("not ready ❌{}", issues)
+            }
+        };
+
+        let lines = inventory
+            .format()
+            .iter()
+            .map(|(label, value)| format!("*{}*: {}", label, markdown::replace_specail_chars(value)))
+            .collect::<Vec<String>>();
+        let mut text = format!(
+            "🧰 *Inventory of {}*\n{}\n\n*RandomX*: {}",
+            markdown::replace_specail_chars(&heartbeat::rig_name()),
+            lines.join("\n"),
+            format_readiness(&inventory.randomx_issues())
+        );
+
+        let mut rigs = self.heartbeats.lock().unwrap().values().cloned().collect::<Vec<_>>();
+        rigs.sort_by(|first, second| first.rig.cmp(&second.rig));
+        if !rigs.is_empty() {
+            let rigs = rigs
+                .iter()
+                .map(|rig| {
+                    let readiness = match &rig.randomx_issues {
+                        Some(issues) => format_readiness(issues),
+                        None => "unknown".to_string(),
+                    };
+                    format!("*{}*: {}", markdown::replace_specail_chars(&rig.rig), readiness)
+                })
+                .collect::<Vec<String>>();
+            text += &format!("\n\n*RandomX on other rigs*\n{}", rigs.join("\n"));
+        }
+
+        let keyboard = vec![vec![InlineKeyboardButton::callback("Back 🔙".to_owned(), "back_to_home")]];
+        self.update_supervisor_message(bot, msg, text, InlineKeyboardMarkup::new(keyboard)).await?;
+        bot.answer_callback_query(&query.id).await?;
+        Ok(())
+    }
+
+    pub async fn plugs_handler(&self, bot: &Bot, msg: &Message, query: &CallbackQuery) -> Result<(), teloxide::RequestError> {
+        let mut keyboard = self
+            .plugs
+            .rigs()
+            .into_iter()
+            .map(|rig| vec![InlineKeyboardButton::callback(rig.clone(), format!("plug_{}", rig))])
+            .collect::<Vec<Vec<InlineKeyboardButton>>>();
+        keyboard.push(vec![InlineKeyboardButton::callback("Back 🔙".to_owned(), "back_to_home")]);
+
+        let text = "🔌 *Power*\nChoose the rig to switch its plug\\.".to_string();
+        self.update_supervisor_message(bot, msg, text, InlineKeyboardMarkup::new(keyboard)).await?;
+        bot.answer_callback_query(&query.id).await?;
+        Ok(())
+    }
+
+    pub async fn plug_handler(&self, bot: &Bot, msg: &Message, query: &CallbackQuery, rig: &str) -> Result<(), teloxide::RequestError> {
+        let plugs = self.plugs.clone();
+        let rig_name = rig.to_string();
+        let state = tokio::task::spawn_blocking(move || plugs.state(&rig_name).map_err(|error| error.to_string()))
+            .await
+            .unwrap_or_else(|error| Err(error.to_string()));
+
+        let state = match state {
+            Ok(true) => "on ✅".to_string(),
+            Ok(false) => "off ❌".to_string(),
+            Err(error) => format!("unknown, {}", error),
+        };
+        let text = format!(
+            "🔌 Plug of *{}*: {}",
+            markdown::replace_specail_chars(rig),
+            markdown::replace_specail_chars(&state)
+        );
+
+        let keyboard = vec![
+            vec![
+                InlineKeyboardButton::callback("Power off".to_owned(), format!("plug_{}_off", rig)),
+                InlineKeyboardButton::callback("Power on".to_owned(), format!("plug_{}_on", rig)),
+            ],
+            vec![InlineKeyboardButton::callback("Power cycle 🔄".to_owned(), format!("plug_{}_cycle", rig))],
+            vec![InlineKeyboardButton::callback("Back 🔙".to_owned(), "plugs")],
+        ];
+
+        self.update_supervisor_message(bot, msg, text, InlineKeyboardMarkup::new(keyboard)).await?;
+        bot.answer_callback_query(&query.id).await?;
+        Ok(())
+    }
+
+    pub async fn plug_confirm_handler(&self, bot: &Bot, msg: &Message, query: &CallbackQuery, rig: &str, action: &str) -> Result<(), teloxide::RequestError> {
+        let mut text = format!("Power {} *{}*?", action, markdown::replace_specail_chars(rig));
+        if rig == heartbeat::rig_name() {
+            text += match PlugAction::parse(action) {
+                Some(PlugAction::Off) => "\nThis is the rig the bot runs on, it can't power it on again\\.",
+                _ => "\nThis is the rig the bot runs on, it will be unreachable for a while\\.",
+            };
+        }
+
+        let keyboard = vec![vec![
+            InlineKeyboardButton::callback("Yes ✅".to_owned(), format!("plug_{}_{}_confirm", rig, action)),
+            InlineKeyboardButton::callback("No ❌".to_owned(), format!("plug_{}", rig)),
+        ]];
+
+        self.update_supervisor_message(bot, msg, text, InlineKeyboardMarkup::new(keyboard)).await?;
+        bot.answer_callback_query(&query.id).await?;
+        Ok(())
+    }
+
+    pub async fn plug_action_handler(&self, bot: &Bot, msg: &Message, query: &CallbackQuery, rig: &str, action: &str) -> Result<(), teloxide::RequestError> {
+        let Some(plug_action) = PlugAction::parse(action) else {
+            return Ok(());
+        };
+        bot.answer_callback_query(&query.id).await?;
+
+        let plugs = self.plugs.clone();
+        let rig_name = rig.to_string();
+        let result = tokio::task::spawn_blocking(move || plugs.power(&rig_name, plug_action).map_err(|error| error.to_string()))
+            .await
+            .unwrap_or_else(|error| Err(error.to_string()));
+
+        let text = match result {
+            Ok(_) => format!("✅ Powered {} *{}*\\.", action, markdown::replace_specail_chars(rig)),
+            Err(error) => {
+                log::error!("Error in power {} rig {}. message: {}", action, rig, error);
+                format!(
+                    "❌ Powering {} *{}* failed: {}",
+                    action,
+                    markdown::replace_specail_chars(rig),
+                    markdown::replace_specail_chars(&error)
+                )
+            }
+        };
+
+        let keyboard = vec![vec![InlineKeyboardButton::callback("Back 🔙".to_owned(), format!("plug_{}", rig))]];
+        self.update_supervisor_message(bot, msg, text, InlineKeyboardMarkup::new(keyboard)).await?;
+        Ok(())
+    }
+
+    pub async fn maintenance_handler(&self, bot: &Bot, msg: &Message, query: &CallbackQuery) -> Result<(), teloxide::RequestError> {
+        let commands = self
+            .maintenance
+            .commands()
+            .iter()
+            .map(|command| format!("*{}*: `{}`", markdown::replace_specail_chars(&command.name), markdown::escape_code(&command.command.join(" "))))
+            .collect::<Vec<String>>();
+
+        let mut keyboard = self
+            .maintenance
+            .commands()
+            .iter()
+            .map(|command| vec![InlineKeyboardButton::callback(command.name.clone(), format!("maintenance_run_{}", command.name))])
+            .collect::<Vec<Vec<InlineKeyboardButton>>>();
+        keyboard.push(vec![InlineKeyboardButton::callback("Back 🔙".to_owned(), "back_to_home")]);
+
+        let text = format!("🛠 *Maintenance*\n\n{}\n\nChoose the command to run\\.", commands.join("\n"));
+
+        self.update_supervisor_message(bot, msg, text, InlineKeyboardMarkup::new(keyboard)).await?;
+        bot.answer_callback_query(&query.id).await?;
+        Ok(())
+    }
+
+    pub async fn maintenance_run_handler(&self, bot: &Bot, msg: &Message, query: &CallbackQuery, command_name: &str) -> Result<(), teloxide::RequestError> {
+        bot.answer_callback_query(&query.id)
+            .text(format!("Running {}, this may take a moment.", command_name))
+            .await?;
+
+        let keyboard = InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback("Back 🔙".to_owned(), "maintenance")]]);
+        let text = format!("⏳ Running *{}*\\.\\.\\.", markdown::replace_specail_chars(command_name));
+        self.update_supervisor_message(bot, msg, text, keyboard.clone()).await?;
+
+        // Commands can run for minutes, so they run in the background and
+        // the message is updated once they finish.
+        let bot = bot.clone();
+        let msg = msg.clone();
+        let maintenance = self.maintenance.clone();
+        let command_name = command_name.to_string();
+        tokio::spawn(async move {
+            let text = match maintenance.run(&command_name).await {
+                Ok(result) => format!(
+                    "{} *{}* {} after {}s",
+                    if result.success { "✅" } else { "❌" },
+                    markdown::replace_specail_chars(&command_name),
+                    match result.status {
+                        _ if result.timed_out => "was killed at its timeout".to_string(),
+                        Some(code) => format!("exited with code {}", code),
+                        None => "was killed by a signal".to_string(),
+                    },
+                    markdown::replace_specail_chars(&format!("{:.1}", result.elapsed.as_secs_f64()))
+                ) + &if result.output.is_empty() {
+                    "\\.".to_string()
+                } else {
+                    format!(":\n```\n{}\n```", markdown::escape_code(&result.output))
+                },
+                Err(error) => {
+                    log::error!("Error in run maintenance command {}. message: {}", command_name, error);
+                    format!(
+                        "❌ Running *{}* failed: {}",
+                        markdown::replace_specail_chars(&command_name),
+                        markdown::replace_specail_chars(&error.to_string())
+                    )
+                }
+            };
+
+            let result = bot
+                .edit_message_text(msg.chat.id, msg.id, text)
+                .parse_mode(ParseMode::MarkdownV2)
+                .reply_markup(keyboard)
+                .await;
+            if let Err(error) = result {
+                log::error!("Error in report maintenance command {}. message: {}", command_name, error);
+            }
+        });
+        Ok(())
+    }
+
     pub async fn supervisor_manager_handler(&self, bot: &Bot, msg: &Message, query: &CallbackQuery, supervisor_name: &str) -> Result<(), teloxide::RequestError> {
         self.handle_supervisor_action(bot, msg, query, supervisor_name, "manage").await
     }
 
+    /// Answers the query with why the program can't be started when it's held
+    /// by the thermal protection.
+    async fn refuse_held(&self, bot: &Bot, query: &CallbackQuery, supervisor_name: &str) -> Result<bool, teloxide::RequestError> {
+        if !self.is_held(supervisor_name) {
+            return Ok(false);
+        }
+        bot.answer_callback_query(&query.id)
+            .text(format!("{} was stopped for overheating and starts again once the rig cooled down.", supervisor_name))
+            .show_alert(true)
+            .await?;
+        Ok(true)
+    }
+
     pub async fn supervisor_start_handler(&self, bot: &Bot, msg: &Message, query: &CallbackQuery, supervisor_name: &str) -> Result<(), teloxide::RequestError> {
+        if self.refuse_held(bot, query, supervisor_name).await? {
+            return Ok(());
+        }
+        let process_list = &self.get_supervisor_process_list().await;
+        let conflicts = self.conflicting_programs(process_list, supervisor_name);
+
+        if conflicts.is_empty() {
+            return self.handle_supervisor_action(bot, msg, query, supervisor_name, "start").await;
+        }
+
+        let text = format!(
+            "*{}* can't run together with *{}*\\. Stop {} and start *{}*?",
+            markdown::replace_specail_chars(supervisor_name),
+            markdown::replace_specail_chars(&conflicts.join(", ")),
+            if conflicts.len() == 1 { "it" } else { "them" },
+            markdown::replace_specail_chars(supervisor_name)
+        );
+        let keyboard = vec![vec![
+            InlineKeyboardButton::callback("Yes ✅".to_owned(), format!("exclusive_start_{}", supervisor_name)),
+            InlineKeyboardButton::callback("No ❌".to_owned(), format!("supervisor_{}", supervisor_name)),
+        ]];
+
+        self.update_supervisor_message(bot, msg, text, InlineKeyboardMarkup::new(keyboard)).await?;
+        bot.answer_callback_query(&query.id).await?;
+        Ok(())
+    }
+
+    pub async fn supervisor_exclusive_start_handler(&self, bot: &Bot, msg: &Message, query: &CallbackQuery, supervisor_name: &str) -> Result<(), teloxide::RequestError> {
+        if self.refuse_held(bot, query, supervisor_name).await? {
+            return Ok(());
+        }
+        let process_list = &self.get_supervisor_process_list().await;
+        let conflicts = self.conflicting_programs(process_list, supervisor_name);
+
+        let supervisor_service = self.supervisor_service.clone();
+        let stopped = tokio::task::spawn_blocking(move || {
+            let mut all_stopped = true;
+            for program in conflicts {
+                all_stopped &= supervisor_service.stop_process(program);
+            }
+            all_stopped
+        })
+        .await
+        .unwrap();
+
+        if !stopped {
+            bot.answer_callback_query(&query.id)
+                .text(format!("Error in stop the programs conflicting with {}.", supervisor_name))
+                .show_alert(true)
+                .await?;
+            return Ok(());
+        }
+
         self.handle_supervisor_action(bot, msg, query, supervisor_name, "start").await
     }
 
@@ -215,8 +1015,8 @@ impl BotHandler {
                 .await?;
 
             let process_list = &self.get_supervisor_process_list().await;
-            let text = self.format_supervisor_status(&process_list);
-            let keyboard = self.create_supervisor_keyboard(&process_list);
+            let text = self.format_supervisor_status(process_list).await;
+            let keyboard = self.create_supervisor_keyboard(process_list);
 
             self.update_supervisor_message(bot, msg, text, keyboard).await?;
         }
@@ -226,24 +1026,31 @@ impl BotHandler {
 
     pub async fn supervisor_start_all_handler(&self, bot: &Bot, msg: &Message, query: &CallbackQuery) -> Result<(), teloxide::RequestError> {
         let supervisor_service = self.supervisor_service.clone();
-        let start_all_status = tokio::task::spawn_blocking(move || supervisor_service.start_all_process())
-            .await
-            .unwrap();
+        let start_all_status = if self.config.exclusion_groups.is_empty() && self.thermal_hold.lock().unwrap().is_empty() {
+            tokio::task::spawn_blocking(move || supervisor_service.start_all_process())
+                .await
+                .unwrap()
+        } else {
+            let programs = self.programs_to_start_all(&self.get_supervisor_process_list().await);
+            tokio::task::spawn_blocking(move || supervisor_service.start_processes(programs))
+                .await
+                .unwrap()
+        };
 
         if !start_all_status {
             bot.answer_callback_query(&query.id)
-                .text("Error in stop all supervisor programs.")
+                .text("Error in start all supervisor programs.")
                 .show_alert(true)
                 .await?;
         } else {
             bot.answer_callback_query(&query.id)
-                .text("All supervisor programs stopped successfully ✅.")
+                .text("All supervisor programs started successfully ✅.")
                 .show_alert(true)
                 .await?;
 
             let process_list = &self.get_supervisor_process_list().await;
-            let text = self.format_supervisor_status(&process_list);
-            let keyboard = self.create_supervisor_keyboard(&process_list);
+            let text = self.format_supervisor_status(process_list).await;
+            let keyboard = self.create_supervisor_keyboard(process_list);
 
             self.update_supervisor_message(bot, msg, text, keyboard).await?;
         }
@@ -269,12 +1076,18 @@ impl BotHandler {
                 .await?;
 
             let process_list = &self.get_supervisor_process_list().await;
-            let text = self.format_supervisor_status(&process_list);
-            let keyboard = self.create_supervisor_keyboard(&process_list);
+            let text = self.format_supervisor_status(process_list).await;
+            let keyboard = self.create_supervisor_keyboard(process_list);
 
             self.update_supervisor_message(bot, msg, text, keyboard).await?;
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Whether supervisord runs the program or is about to, including while it
+/// respawns it after a crash.
+fn is_active(program: &Process) -> bool {
+    !matches!(program.state.as_str(), "STOPPED" | "EXITED" | "FATAL")
+}
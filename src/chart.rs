@@ -0,0 +1,227 @@
+use chrono::{Local, TimeZone};
+
+use crate::{miners, samples::Sample};
+
+const WIDTH: usize = 800;
+const HEIGHT: usize = 400;
+const LEFT: usize = 90;
+const RIGHT: usize = 20;
+const TOP: usize = 20;
+const BOTTOM: usize = 40;
+const FONT_SCALE: usize = 2;
+
+const BACKGROUND: [u8; 3] = [255, 255, 255];
+const GRID: [u8; 3] = [225, 225, 225];
+const AXIS: [u8; 3] = [90, 90, 90];
+const LINE: [u8; 3] = [33, 113, 181];
+const DOWNTIME: [u8; 3] = [250, 205, 205];
+
+/// Renders the hashrate of a program over `[from, to]` as a PNG, with the
+/// periods it wasn't running shaded. Samples without a hashrate break the line.
+pub fn render_hashrate_chart(
+    samples: &[&Sample],
+    downtime: &[(i64, i64)],
+    from: i64,
+    to: i64,
+) -> Result<Vec<u8>, png::EncodingError> {
+    let mut canvas = Canvas::new();
+    let plot_width = WIDTH - LEFT - RIGHT;
+    let plot_height = HEIGHT - TOP - BOTTOM;
+
+    let max_hashrate = samples
+        .iter()
+        .filter_map(|sample| sample.hashrate)
+        .fold(0.0, f64::max);
+    let scale_max = if max_hashrate > 0.0 {
+        max_hashrate * 1.1
+    } else {
+        1.0
+    };
+
+    let x_of = |timestamp: i64| {
+        LEFT + ((timestamp - from).clamp(0, to - from) as f64 / (to - from) as f64
+            * plot_width as f64) as usize
+    };
+    let y_of = |hashrate: f64| {
+        TOP + plot_height - ((hashrate / scale_max).min(1.0) * plot_height as f64) as usize
+    };
+
+    for (start, end) in downtime {
+        canvas.fill_rect(
+            x_of(*start),
+            TOP,
+            x_of(*end).max(x_of(*start) + 1),
+            TOP + plot_height,
+            DOWNTIME,
+        );
+    }
+
+    for step in 0..=4 {
+        let hashrate = scale_max * step as f64 / 4.0;
+        let y = y_of(hashrate);
+        canvas.fill_rect(LEFT, y, LEFT + plot_width, y + 1, GRID);
+        canvas.text(
+            4,
+            y.saturating_sub(5 * FONT_SCALE / 2),
+            &miners::format_hashrate(hashrate),
+            AXIS,
+        );
+    }
+
+    let time_format = if to - from > 2 * 86400 {
+        "%m-%d"
+    } else {
+        "%H:%M"
+    };
+    for step in 0..=4 {
+        let timestamp = from + (to - from) * step / 4;
+        let x = x_of(timestamp);
+        canvas.fill_rect(x, TOP, x + 1, TOP + plot_height, GRID);
+
+        let label = match Local.timestamp_opt(timestamp, 0).single() {
+            Some(time) => time.format(time_format).to_string(),
+            None => continue,
+        };
+        let label_width = label.len() * 4 * FONT_SCALE;
+        let label_x = x
+            .saturating_sub(label_width / 2)
+            .min(WIDTH - label_width - 1);
+        canvas.text(label_x, TOP + plot_height + 10, &label, AXIS);
+    }
+
+    canvas.fill_rect(LEFT, TOP, LEFT + 1, TOP + plot_height + 1, AXIS);
+    canvas.fill_rect(
+        LEFT,
+        TOP + plot_height,
+        LEFT + plot_width + 1,
+        TOP + plot_height + 1,
+        AXIS,
+    );
+
+    let mut previous: Option<(usize, usize)> = None;
+    for sample in samples {
+        let point = sample
+            .hashrate
+            .map(|hashrate| (x_of(sample.timestamp), y_of(hashrate)));
+        if let (Some(start), Some(end)) = (previous, point) {
+            canvas.line(start, end, LINE);
+        }
+        previous = point;
+    }
+
+    canvas.encode()
+}
+
+struct Canvas {
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new() -> Self {
+        Canvas {
+            pixels: BACKGROUND.repeat(WIDTH * HEIGHT),
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize, color: [u8; 3]) {
+        if x < WIDTH && y < HEIGHT {
+            let index = (y * WIDTH + x) * 3;
+            self.pixels[index..index + 3].copy_from_slice(&color);
+        }
+    }
+
+    fn fill_rect(&mut self, x1: usize, y1: usize, x2: usize, y2: usize, color: [u8; 3]) {
+        for y in y1..y2 {
+            for x in x1..x2 {
+                self.set(x, y, color);
+            }
+        }
+    }
+
+    /// Bresenham's line, drawn two pixels thick.
+    fn line(&mut self, start: (usize, usize), end: (usize, usize), color: [u8; 3]) {
+        let (mut x, mut y) = (start.0 as i64, start.1 as i64);
+        let (x2, y2) = (end.0 as i64, end.1 as i64);
+        let dx = (x2 - x).abs();
+        let dy = -(y2 - y).abs();
+        let step_x = if x < x2 { 1 } else { -1 };
+        let step_y = if y < y2 { 1 } else { -1 };
+        let mut error = dx + dy;
+
+        loop {
+            self.set(x as usize, y as usize, color);
+            self.set(x as usize, y as usize + 1, color);
+            if x == x2 && y == y2 {
+                break;
+            }
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    fn text(&mut self, x: usize, y: usize, text: &str, color: [u8; 3]) {
+        for (index, character) in text.chars().enumerate() {
+            let origin_x = x + index * 4 * FONT_SCALE;
+            for (row, bits) in glyph(character).iter().enumerate() {
+                for column in 0..3 {
+                    if bits & (0b100 >> column) != 0 {
+                        self.fill_rect(
+                            origin_x + column * FONT_SCALE,
+                            y + row * FONT_SCALE,
+                            origin_x + (column + 1) * FONT_SCALE,
+                            y + (row + 1) * FONT_SCALE,
+                            color,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn encode(&self) -> Result<Vec<u8>, png::EncodingError> {
+        let mut bytes = vec![];
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, WIDTH as u32, HEIGHT as u32);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header()?;
+            writer.write_image_data(&self.pixels)?;
+        }
+        Ok(bytes)
+    }
+}
+
+/// A 3x5 pixel font covering the characters of axis labels.
+fn glyph(character: char) -> [u8; 5] {
+    match character {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        'k' => [0b100, 0b101, 0b110, 0b101, 0b101],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'G' => [0b111, 0b100, 0b101, 0b101, 0b111],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'P' => [0b111, 0b101, 0b111, 0b100, 0b100],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        's' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        _ => [0; 5],
+    }
+}
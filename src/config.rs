@@ -0,0 +1,478 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+
+use serde::Deserialize;
+
+use crate::alert::Severity;
+
+/// Telegram rejects buttons with more callback data than this, in bytes.
+const MAX_CALLBACK_DATA: usize = 64;
+
+/// Per-program settings that don't fit in environment variables, read from the
+/// JSON file given by `CONFIG_FILE` (`config.json` by default).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub programs: HashMap<String, ProgramConfig>,
+    pub alerts: AlertsConfig,
+    /// Pool profiles miners can be switched between, by name.
+    pub pools: HashMap<String, PoolProfile>,
+    pub profit: Option<ProfitConfig>,
+    pub exclusion_groups: Vec<ExclusionGroup>,
+    pub earnings: Option<EarningsConfig>,
+    pub power: Option<PowerConfig>,
+    /// Commands that may be run from the maintenance menu, nothing else can.
+    pub maintenance: Vec<MaintenanceCommand>,
+    /// Smart plugs the rigs are powered through, by rig name.
+    pub plugs: HashMap<String, SmartPlug>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AlertsConfig {
+    pub routes: Vec<AlertRoute>,
+    /// Alerts below this severity are delivered without a notification sound.
+    pub silent_below: Severity,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        AlertsConfig {
+            routes: vec![],
+            silent_below: Severity::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlertRoute {
+    pub chat_id: i64,
+    #[serde(default = "default_min_severity")]
+    pub min_severity: Severity,
+    #[serde(default = "default_max_severity")]
+    pub max_severity: Severity,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EarningsConfig {
+    /// Local JSON file or HTTP endpoint with the parameters of every coin by name.
+    pub source: String,
+    /// The coin each program mines.
+    pub programs: HashMap<String, String>,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    /// Minutes between refreshes of the coin parameters.
+    #[serde(default = "default_earnings_refresh")]
+    pub refresh: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PowerConfig {
+    /// Watts the rig draws with nothing running.
+    #[serde(default)]
+    pub baseline: f64,
+    /// Watts each program adds on top of the baseline while it runs.
+    #[serde(default)]
+    pub programs: HashMap<String, f64>,
+    /// Price of a kWh.
+    pub tariff: f64,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MaintenanceCommand {
+    pub name: String,
+    /// Program and arguments, run as is without a shell.
+    pub command: Vec<String>,
+    /// Seconds before the command is killed.
+    #[serde(default = "default_maintenance_timeout")]
+    pub timeout: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SmartPlug {
+    #[serde(rename = "type")]
+    pub kind: PlugKind,
+    /// Host name or address of the plug in the local network.
+    pub host: String,
+    /// Relay of the plug, counting from 0.
+    #[serde(default)]
+    pub channel: u32,
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Seconds the rig stays off during a power cycle.
+    #[serde(default = "default_plug_off_time")]
+    pub off_time: u64,
+    /// Minutes without a heartbeat after which the rig is power-cycled automatically.
+    #[serde(default)]
+    pub auto_cycle_after: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlugKind {
+    Tasmota,
+    /// Shelly devices of the first generation, with the `/relay` API.
+    Shelly,
+    /// Shelly Plus and Pro devices, with the RPC API.
+    ShellyRpc,
+}
+
+/// Programs that must not run at the same time, like two miners on the same GPUs.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExclusionGroup {
+    pub name: String,
+    pub programs: Vec<String>,
+    /// The member "Start all programs" starts, the first one when unset.
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+impl ExclusionGroup {
+    pub fn default_program(&self) -> Option<&String> {
+        self.default.as_ref().or(self.programs.first())
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ProgramConfig {
+    pub log_rules: Vec<LogRule>,
+    pub stats: Option<StatsSource>,
+    pub reject_rate: Option<RejectRateConfig>,
+    pub low_hashrate: Option<LowHashrateConfig>,
+    pub pool_switch: Option<PoolSwitchConfig>,
+    pub pool_probes: Vec<PoolProbe>,
+    /// Stopped when the rig overheats and started again once it cooled down.
+    pub thermal_stop: bool,
+}
+
+/// A pool endpoint whose reachability and connect latency are checked.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PoolProbe {
+    pub url: String,
+    /// Forces TLS on or off; by default it's used for `ssl` and `tls` schemes.
+    #[serde(default)]
+    pub tls: Option<bool>,
+    /// Logs in with a test worker and waits for a job instead of only connecting.
+    #[serde(default)]
+    pub stratum: Option<StratumCheck>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StratumCheck {
+    #[serde(default)]
+    pub protocol: StratumProtocol,
+    pub user: String,
+    #[serde(default = "default_pool_pass")]
+    pub pass: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StratumProtocol {
+    /// Stratum v1 `mining.subscribe` and `mining.authorize`.
+    #[default]
+    Stratum,
+    /// The `login` method of xmrig and other CryptoNote pools.
+    Xmrig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RejectRateConfig {
+    /// Percentage of rejected and stale shares above which an alert is sent.
+    pub threshold: f64,
+    /// Window in minutes the rate is computed over.
+    #[serde(default = "default_reject_rate_window")]
+    pub window: i64,
+    /// Fewer shares than this in the window are too few to judge.
+    #[serde(default = "default_reject_rate_min_shares")]
+    pub min_shares: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LowHashrateConfig {
+    /// Normal hashrate of the program in H/s.
+    pub expected: f64,
+    /// Percentage below `expected` that still counts as normal.
+    #[serde(default = "default_low_hashrate_tolerance")]
+    pub tolerance: f64,
+    /// Minutes the hashrate has to stay low before alerting.
+    #[serde(default = "default_low_hashrate_duration")]
+    pub duration: i64,
+    #[serde(default)]
+    pub restart: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PoolProfile {
+    pub url: String,
+    #[serde(default)]
+    pub user: String,
+    #[serde(default = "default_pool_pass")]
+    pub pass: String,
+    #[serde(default)]
+    pub tls: bool,
+    #[serde(default)]
+    pub coin: Option<String>,
+    #[serde(default)]
+    pub algo: Option<String>,
+    #[serde(default)]
+    pub rig_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PoolSwitchConfig {
+    #[serde(flatten)]
+    pub target: PoolTarget,
+    /// Profiles offered for the program, all of them when empty.
+    #[serde(default)]
+    pub profiles: Vec<String>,
+}
+
+/// The miner config file a pool profile is written to.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum PoolTarget {
+    /// The `pools` array of an xmrig `config.json`.
+    Xmrig { path: String },
+    /// A file rendered from a template with `{url}`, `{user}`, `{pass}`,
+    /// `{coin}`, `{algo}` and `{rig_id}` placeholders.
+    Template { template: String, path: String },
+}
+
+impl PoolTarget {
+    pub fn path(&self) -> &str {
+        match self {
+            PoolTarget::Xmrig { path } | PoolTarget::Template { path, .. } => path,
+        }
+    }
+}
+
+/// Switching between miner programs of different algorithms by profitability.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProfitConfig {
+    /// Local JSON file or HTTP endpoint with the revenue per day of 1 H/s by algorithm.
+    pub source: String,
+    pub programs: HashMap<String, ProfitProgram>,
+    /// Percentage a program has to beat the running one by before switching.
+    #[serde(default = "default_profit_hysteresis")]
+    pub hysteresis: f64,
+    /// Minutes a program runs at least before it's switched away from.
+    #[serde(default = "default_profit_min_dwell")]
+    pub min_dwell: i64,
+    /// Minutes between profitability checks.
+    #[serde(default = "default_profit_interval")]
+    pub interval: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProfitProgram {
+    pub algorithm: String,
+    /// Hashrate in H/s to assume until the program has been measured.
+    #[serde(default)]
+    pub hashrate: Option<f64>,
+}
+
+/// Where the hashrate and share statistics of a program come from.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum StatsSource {
+    Xmrig {
+        url: String,
+        #[serde(default)]
+        access_token: Option<String>,
+    },
+    Cgminer {
+        host: String,
+        #[serde(default = "default_cgminer_port")]
+        port: u16,
+    },
+    /// Parsed from the program's own output, for miners without an API.
+    Log {
+        parser: String,
+        #[serde(default)]
+        patterns: Vec<String>,
+        #[serde(default)]
+        unit: Option<String>,
+        #[serde(default)]
+        stream: LogStream,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LogRule {
+    pub pattern: String,
+    #[serde(default = "default_severity")]
+    pub severity: Severity,
+    #[serde(default)]
+    pub stream: LogStream,
+    /// Seconds to wait before the same rule alerts (and acts) again.
+    #[serde(default = "default_cooldown")]
+    pub cooldown: i64,
+    #[serde(default)]
+    pub action: Option<LogAction>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    #[default]
+    Stdout,
+    Stderr,
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogAction {
+    Restart,
+    Stop,
+}
+
+impl LogStream {
+    pub fn covers(&self, stream: LogStream) -> bool {
+        *self == stream || *self == LogStream::Both
+    }
+}
+
+impl LogAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LogAction::Restart => "restart",
+            LogAction::Stop => "stop",
+        }
+    }
+}
+
+impl Config {
+    pub fn exclusion_group(&self, program: &str) -> Option<&ExclusionGroup> {
+        self.exclusion_groups
+            .iter()
+            .find(|group| group.programs.iter().any(|member| member == program))
+    }
+
+    /// Read once at startup and handed to every service. A missing file means
+    /// the defaults, an invalid one is an error.
+    pub fn load() -> Result<Self, String> {
+        let path = env::var("CONFIG_FILE").unwrap_or_else(|_| "config.json".to_string());
+
+        let config: Config = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|error| format!("invalid config file {}: {}", path, error))?,
+            Err(_) => return Ok(Config::default()),
+        };
+        config
+            .check_callback_data()
+            .map_err(|error| format!("invalid config file {}: {}", path, error))?;
+        Ok(config)
+    }
+
+    /// Names end up in the callback data of the bot's buttons, which must fit
+    /// in `MAX_CALLBACK_DATA` bytes.
+    fn check_callback_data(&self) -> Result<(), String> {
+        let mut callback_data = vec![];
+        for (program, program_config) in &self.programs {
+            callback_data.push((format!("chart_{}_24h", program), program));
+            if let Some(pool_switch) = &program_config.pool_switch {
+                let profiles = if pool_switch.profiles.is_empty() {
+                    self.pools.keys().collect::<Vec<&String>>()
+                } else {
+                    pool_switch.profiles.iter().collect()
+                };
+                for profile in profiles {
+                    callback_data.push((format!("pool_{}_use_{}", program, profile), profile));
+                }
+            }
+        }
+        for command in &self.maintenance {
+            callback_data.push((format!("maintenance_run_{}", command.name), &command.name));
+        }
+        for rig in self.plugs.keys() {
+            callback_data.push((format!("plug_{}_cycle_confirm", rig), rig));
+        }
+
+        match callback_data
+            .into_iter()
+            .find(|(data, _)| data.len() > MAX_CALLBACK_DATA)
+        {
+            Some((data, name)) => Err(format!(
+                "{} is too long, the button data {} exceeds the {} bytes Telegram allows",
+                name, data, MAX_CALLBACK_DATA
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+fn default_reject_rate_window() -> i64 {
+    60
+}
+
+fn default_reject_rate_min_shares() -> u64 {
+    10
+}
+
+fn default_low_hashrate_tolerance() -> f64 {
+    10.0
+}
+
+fn default_low_hashrate_duration() -> i64 {
+    10
+}
+
+fn default_currency() -> String {
+    "USD".to_string()
+}
+
+fn default_maintenance_timeout() -> u64 {
+    60
+}
+
+fn default_plug_off_time() -> u64 {
+    10
+}
+
+fn default_earnings_refresh() -> i64 {
+    15
+}
+
+fn default_profit_hysteresis() -> f64 {
+    10.0
+}
+
+fn default_profit_min_dwell() -> i64 {
+    30
+}
+
+fn default_profit_interval() -> i64 {
+    10
+}
+
+fn default_pool_pass() -> String {
+    "x".to_string()
+}
+
+fn default_cgminer_port() -> u16 {
+    4028
+}
+
+fn default_min_severity() -> Severity {
+    Severity::Info
+}
+
+fn default_max_severity() -> Severity {
+    Severity::Critical
+}
+
+fn default_severity() -> Severity {
+    Severity::Warning
+}
+
+fn default_cooldown() -> i64 {
+    600
+}
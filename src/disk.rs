@@ -0,0 +1,257 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::{Local, NaiveDate};
+
+use crate::{
+    alert::{AlertService, Severity},
+    supervisor::SupervisorService,
+    utils::{logger, markdown},
+};
+
+const LOG_DIR: &str = "log";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum SpaceLevel {
+    Normal,
+    Low,
+    Critical,
+}
+
+/// Watches free space on `DISK_MOUNTS` and keeps the logs in check: the bot's
+/// own daily logs are pruned after `LOG_RETENTION_DAYS`, and the supervisor
+/// program logs are cleared once space gets critical.
+pub struct DiskService {
+    supervisor_service: SupervisorService,
+    alert_service: AlertService,
+    mounts: Vec<String>,
+    interval: Duration,
+    warning: f64,
+    critical: f64,
+    log_retention_days: i64,
+    levels: Vec<SpaceLevel>,
+}
+
+impl DiskService {
+    pub fn new(alert_service: AlertService) -> Self {
+        let env_or = |key: &str, default: f64| {
+            env::var(key)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        };
+
+        let mut mounts: Vec<String> = env::var("DISK_MOUNTS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|mount| !mount.is_empty())
+            .map(String::from)
+            .collect();
+        if mounts.is_empty() {
+            mounts.push("/".to_string());
+        }
+
+        DiskService {
+            supervisor_service: SupervisorService::new(),
+            alert_service,
+            levels: vec![SpaceLevel::Normal; mounts.len()],
+            mounts,
+            interval: Duration::from_secs(env_or("DISK_INTERVAL", 300.0) as u64),
+            warning: env_or("DISK_WARNING", 10.0),
+            critical: env_or("DISK_CRITICAL", 5.0),
+            log_retention_days: (env_or("LOG_RETENTION_DAYS", 30.0) as i64).max(1),
+        }
+    }
+
+    pub async fn run(&mut self) {
+        loop {
+            self.housekeeping().await;
+            self.check().await;
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    async fn housekeeping(&self) {
+        let retention_days = self.log_retention_days;
+        let removed = tokio::task::spawn_blocking(move || prune_bot_logs(retention_days))
+            .await
+            .unwrap_or_default();
+
+        if removed.is_empty() {
+            return;
+        }
+
+        log::info!("Removed old bot logs: {:?}", removed);
+        self.alert_service
+            .send(
+                Severity::Info,
+                format!(
+                    "🧹 Removed {} old bot log{}: {}",
+                    removed.len(),
+                    if removed.len() == 1 { "" } else { "s" },
+                    markdown::replace_specail_chars(&removed.join(", "))
+                ),
+            )
+            .await;
+    }
+
+    async fn check(&mut self) {
+        for index in 0..self.mounts.len() {
+            let mount = self.mounts[index].clone();
+            let free_percent = match read_free_percent(&mount) {
+                Some(free_percent) => free_percent,
+                None => continue,
+            };
+
+            let level = if free_percent < self.critical {
+                SpaceLevel::Critical
+            } else if free_percent < self.warning {
+                SpaceLevel::Low
+            } else {
+                SpaceLevel::Normal
+            };
+            let previous = std::mem::replace(&mut self.levels[index], level);
+            if level == previous {
+                continue;
+            }
+
+            let free = markdown::replace_specail_chars(&format!("{:.1}%", free_percent));
+            let mount_name = markdown::replace_specail_chars(&mount);
+            match level {
+                SpaceLevel::Critical => {
+                    let cleared = self.clear_program_logs().await;
+                    let freed = read_free_percent(&mount).unwrap_or(free_percent) - free_percent;
+                    self.alert_service
+                        .send(
+                            Severity::Critical,
+                            format!(
+                                "💾 Only {} free on *{}*\\. {}",
+                                free,
+                                mount_name,
+                                if cleared.is_empty() {
+                                    "No program logs could be cleared\\.".to_string()
+                                } else {
+                                    format!(
+                                        "Cleared the logs of *{}*, freeing {} of the disk\\.",
+                                        markdown::replace_specail_chars(&cleared.join(", ")),
+                                        markdown::replace_specail_chars(&format!(
+                                            "{:.1}%",
+                                            freed.max(0.0)
+                                        ))
+                                    )
+                                }
+                            ),
+                        )
+                        .await;
+                }
+                SpaceLevel::Low if previous < level => {
+                    self.alert_service
+                        .send(
+                            Severity::Warning,
+                            format!("💾 Only {} free on *{}*\\.", free, mount_name),
+                        )
+                        .await;
+                }
+                SpaceLevel::Low => {
+                    self.alert_service
+                        .send(
+                            Severity::Warning,
+                            format!(
+                                "💾 *{}* is no longer critical but still low on space: {} free\\.",
+                                mount_name, free
+                            ),
+                        )
+                        .await;
+                }
+                SpaceLevel::Normal => {
+                    self.alert_service
+                        .send(
+                            Severity::Info,
+                            format!("✅ *{}* has {} free again\\.", mount_name, free),
+                        )
+                        .await;
+                }
+            }
+        }
+    }
+
+    /// Clears the stdout and stderr logs of every supervisor program and returns
+    /// the programs whose logs were cleared.
+    async fn clear_program_logs(&self) -> Vec<String> {
+        let supervisor_service = self.supervisor_service.clone();
+        let cleared =
+            tokio::task::spawn_blocking(move || match supervisor_service.try_process_list() {
+                Ok(process_list) => process_list
+                    .iter()
+                    .filter(|process| supervisor_service.clear_process_logs(process))
+                    .map(|process| process.full_name())
+                    .collect(),
+                Err(error) => {
+                    log::error!("Error in list programs to clear logs. message: {}", error);
+                    vec![]
+                }
+            })
+            .await
+            .unwrap_or_default();
+
+        log::warn!("Disk space critical, cleared program logs: {:?}", cleared);
+        cleared
+    }
+}
+
+fn read_free_percent(mount: &str) -> Option<f64> {
+    let total = fs2::total_space(mount);
+    let available = fs2::available_space(mount);
+
+    match (total, available) {
+        (Ok(total), Ok(available)) if total > 0 => Some(available as f64 * 100.0 / total as f64),
+        (Err(error), _) | (_, Err(error)) => {
+            log::error!("Error in read free space of {}. message: {}", mount, error);
+            None
+        }
+        _ => None,
+    }
+}
+
+/// Removes `log/YYYY-MM-DD.log` files older than the retention and returns their
+/// names. The file the bot is logging to is kept however old it is.
+fn prune_bot_logs(retention_days: i64) -> Vec<String> {
+    let cutoff = Local::now().date_naive() - chrono::Duration::days(retention_days);
+    let current_file = logger::current_file().map(PathBuf::from);
+
+    let entries: Vec<PathBuf> = match fs::read_dir(LOG_DIR) {
+        Ok(entries) => entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .collect(),
+        Err(_) => return vec![],
+    };
+
+    let mut removed = vec![];
+    for path in entries {
+        let file_name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let date = file_name
+            .strip_suffix(".log")
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok());
+
+        let in_use = current_file
+            .as_ref()
+            .is_some_and(|current_file| current_file.file_name() == path.file_name());
+        if date.is_some_and(|date| date < cutoff) && !in_use {
+            match fs::remove_file(&path) {
+                Ok(_) => removed.push(file_name),
+                Err(error) => {
+                    log::error!("Error in remove {}. message: {}", path.display(), error)
+                }
+            }
+        }
+    }
+    removed.sort();
+    removed
+}
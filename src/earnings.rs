@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::{
+    config::{Config, EarningsConfig},
+    history::StateHistory,
+    samples::SampleStore,
+    utils::json_source,
+};
+
+/// Network and market parameters of a coin, as read from `earnings.source`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CoinParams {
+    #[serde(default)]
+    pub symbol: Option<String>,
+    /// Network hashrate in H/s; derived from `difficulty` when missing.
+    #[serde(default)]
+    pub network_hashrate: Option<f64>,
+    #[serde(default)]
+    pub difficulty: Option<f64>,
+    /// Hashes per unit of difficulty, 2^32 for bitcoin-style difficulty.
+    #[serde(default = "default_difficulty_multiplier")]
+    pub difficulty_multiplier: f64,
+    pub block_reward: f64,
+    /// Seconds between blocks.
+    pub block_time: f64,
+    #[serde(default)]
+    pub price: Option<f64>,
+    /// Pool fee in percent.
+    #[serde(default)]
+    pub pool_fee: f64,
+}
+
+impl CoinParams {
+    pub fn network_hashrate(&self) -> Option<f64> {
+        self.network_hashrate.or_else(|| {
+            self.difficulty
+                .map(|difficulty| difficulty * self.difficulty_multiplier / self.block_time)
+        })
+    }
+
+    /// Coins mined per day at the given hashrate, after the pool fee.
+    pub fn coins_per_day(&self, hashrate: f64) -> Option<f64> {
+        let network_hashrate = self.network_hashrate().filter(|rate| *rate > 0.0)?;
+        let blocks_per_day = 86400.0 / self.block_time;
+
+        Some(
+            hashrate / network_hashrate
+                * blocks_per_day
+                * self.block_reward
+                * (1.0 - self.pool_fee / 100.0),
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Earnings {
+    pub symbol: String,
+    pub coins_per_day: f64,
+    /// Value per day in the configured currency, when the coin has a price.
+    pub value_per_day: Option<f64>,
+}
+
+/// Estimates what every program makes per day from its measured hashrate and
+/// uptime. Coin parameters are refreshed in the background by `run`.
+#[derive(Clone)]
+pub struct EarningsService {
+    config: Option<EarningsConfig>,
+    coins: Arc<Mutex<HashMap<String, CoinParams>>>,
+}
+
+impl EarningsService {
+    pub fn new(config: &Config) -> Self {
+        EarningsService {
+            config: config.earnings.clone(),
+            coins: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub async fn run(&self) {
+        let Some(config) = &self.config else {
+            return;
+        };
+
+        loop {
+            let source = config.source.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                json_source::fetch::<HashMap<String, CoinParams>>(&source)
+                    .map_err(|error| error.to_string())
+            })
+            .await
+            .unwrap();
+
+            match result {
+                Ok(coins) => *self.coins.lock().unwrap() = coins,
+                Err(error) => log::error!("Error in fetch coin parameters. message: {}", error),
+            }
+
+            tokio::time::sleep(Duration::from_secs(config.refresh as u64 * 60)).await;
+        }
+    }
+
+    pub fn currency(&self) -> &str {
+        self.config
+            .as_ref()
+            .map(|config| config.currency.as_str())
+            .unwrap_or_default()
+    }
+
+    /// Expected earnings per day based on the average hashrate and the uptime
+    /// of the program within `[from, to]`.
+    pub fn estimate(
+        &self,
+        history: &StateHistory,
+        samples: &SampleStore,
+        program: &str,
+        from: i64,
+        to: i64,
+    ) -> Option<Earnings> {
+        let coin = self.config.as_ref()?.programs.get(program)?;
+        let params = self.coins.lock().unwrap().get(coin)?.clone();
+
+        let hashrate = samples.average_hashrate(program, from, to)?;
+        let uptime = history.uptime_percent(program, from, to).unwrap_or(100.0) / 100.0;
+        let coins_per_day = params.coins_per_day(hashrate)? * uptime;
+
+        Some(Earnings {
+            symbol: params.symbol.clone().unwrap_or_else(|| coin.clone()),
+            coins_per_day,
+            value_per_day: params.price.map(|price| coins_per_day * price),
+        })
+    }
+
+    /// Total value per day of all programs, `None` when none has a priced estimate.
+    pub fn total_value(
+        &self,
+        history: &StateHistory,
+        samples: &SampleStore,
+        programs: &[String],
+        from: i64,
+        to: i64,
+    ) -> Option<f64> {
+        let values: Vec<f64> = programs
+            .iter()
+            .filter_map(|program| self.estimate(history, samples, program, from, to))
+            .filter_map(|earnings| earnings.value_per_day)
+            .collect();
+
+        if values.is_empty() {
+            None
+        } else {
+            Some(values.iter().sum())
+        }
+    }
+
+    pub fn format(&self, earnings: &Earnings) -> String {
+        match earnings.value_per_day {
+            Some(value) => format!(
+                "{:.6} {}/day ≈ {:.2} {}",
+                earnings.coins_per_day,
+                earnings.symbol,
+                value,
+                self.currency()
+            ),
+            None => format!("{:.6} {}/day", earnings.coins_per_day, earnings.symbol),
+        }
+    }
+}
+
+fn default_difficulty_multiplier() -> f64 {
+    1.0
+}
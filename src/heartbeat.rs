@@ -0,0 +1,491 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    alert::{AlertService, Severity},
+    config::Config,
+    inventory::Inventory,
+    plug::{PlugAction, PlugService},
+    supervisor::SupervisorService,
+    utils::{markdown, timedate},
+};
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(30);
+const MAX_BODY_SIZE: usize = 64 * 1024;
+const MAX_CONNECTIONS: usize = 32;
+const SECRET_HEADER: &str = "X-Heartbeat-Secret";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Heartbeat {
+    pub rig: String,
+    pub timestamp: i64,
+    pub supervisor_reachable: bool,
+    /// Missing RandomX prerequisites, `None` for rigs that don't report them.
+    #[serde(default)]
+    pub randomx_issues: Option<Vec<String>>,
+}
+
+pub type Heartbeats = Arc<Mutex<HashMap<String, Heartbeat>>>;
+
+/// Periodically reports this rig to `HEARTBEAT_TARGET`, which is either an
+/// `http(s)://` URL of a manager instance or a directory shared between rigs.
+pub struct HeartbeatService {
+    supervisor_service: SupervisorService,
+    rig_name: String,
+    target: Option<String>,
+    secret: Option<String>,
+    interval: Duration,
+}
+
+impl HeartbeatService {
+    pub fn new() -> Self {
+        let interval = env::var("HEARTBEAT_INTERVAL")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(60);
+
+        HeartbeatService {
+            supervisor_service: SupervisorService::new(),
+            rig_name: rig_name(),
+            target: env::var("HEARTBEAT_TARGET")
+                .ok()
+                .filter(|value| !value.is_empty()),
+            secret: heartbeat_secret(),
+            interval: Duration::from_secs(interval),
+        }
+    }
+
+    pub async fn run(&self) {
+        let Some(target) = &self.target else {
+            return;
+        };
+
+        loop {
+            let supervisor_service = self.supervisor_service.clone();
+            let rig_name = self.rig_name.clone();
+            let target = target.clone();
+            let secret = self.secret.clone();
+
+            let result = tokio::task::spawn_blocking(move || {
+                let heartbeat = Heartbeat {
+                    rig: rig_name,
+                    timestamp: Utc::now().timestamp(),
+                    supervisor_reachable: supervisor_service.try_process_list().is_ok(),
+                    randomx_issues: Some(Inventory::read().randomx_issues()),
+                };
+                send_heartbeat(&target, secret.as_deref(), &heartbeat)
+            })
+            .await
+            .unwrap_or_else(|error| Err(error.into()));
+
+            if let Err(error) = result {
+                log::error!("Error in send heartbeat. message: {}", error);
+            }
+
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+}
+
+impl Default for HeartbeatService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Watches the heartbeats of other rigs, received on `HEARTBEAT_LISTEN` or read
+/// from `HEARTBEAT_WATCH_DIR`, and alerts when a rig stops reporting or its
+/// supervisor becomes unreachable.
+pub struct HeartbeatWatcher {
+    alert_service: AlertService,
+    listen: Option<String>,
+    secret: Option<String>,
+    watch_dir: Option<PathBuf>,
+    timeout: i64,
+    heartbeats: Heartbeats,
+    silent_rigs: HashSet<String>,
+    supervisor_down_rigs: HashSet<String>,
+    plugs: PlugService,
+    /// Rigs power-cycled in the current outage, so it only happens once.
+    cycled_rigs: HashSet<String>,
+}
+
+impl HeartbeatWatcher {
+    pub fn new(heartbeats: Heartbeats, alert_service: AlertService, config: &Config) -> Self {
+        let timeout: i64 = env::var("HEARTBEAT_TIMEOUT")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(300);
+
+        // Rigs listed in `HEARTBEAT_RIGS` are expected from the start, so a rig
+        // that never reports after the manager restarted is noticed too.
+        let now = Utc::now().timestamp();
+        heartbeats.lock().unwrap().extend(
+            env::var("HEARTBEAT_RIGS")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|rig| !rig.is_empty())
+                .map(|rig| {
+                    (
+                        rig.to_string(),
+                        Heartbeat {
+                            rig: rig.to_string(),
+                            timestamp: now,
+                            supervisor_reachable: true,
+                            randomx_issues: None,
+                        },
+                    )
+                }),
+        );
+
+        HeartbeatWatcher {
+            alert_service,
+            listen: env::var("HEARTBEAT_LISTEN")
+                .ok()
+                .filter(|value| !value.is_empty()),
+            secret: heartbeat_secret(),
+            watch_dir: env::var("HEARTBEAT_WATCH_DIR")
+                .ok()
+                .filter(|value| !value.is_empty())
+                .map(PathBuf::from),
+            timeout,
+            heartbeats,
+            silent_rigs: HashSet::new(),
+            supervisor_down_rigs: HashSet::new(),
+            plugs: PlugService::new(config),
+            cycled_rigs: HashSet::new(),
+        }
+    }
+
+    pub async fn run(&mut self) {
+        if self.listen.is_none() && self.watch_dir.is_none() {
+            return;
+        }
+
+        match (self.listen.clone(), self.secret.clone()) {
+            (Some(address), Some(secret)) => {
+                let heartbeats = self.heartbeats.clone();
+                std::thread::spawn(move || {
+                    if let Err(error) = listen(&address, secret, heartbeats) {
+                        log::error!("Error in heartbeat listener. message: {}", error);
+                    }
+                });
+            }
+            (Some(_), None) => {
+                log::error!("Error in heartbeat listener. message: HEARTBEAT_LISTEN needs a HEARTBEAT_SECRET");
+            }
+            _ => {}
+        }
+
+        loop {
+            if let Some(watch_dir) = &self.watch_dir {
+                let mut heartbeats = self.heartbeats.lock().unwrap();
+                for heartbeat in read_heartbeat_dir(watch_dir) {
+                    heartbeats.insert(heartbeat.rig.clone(), heartbeat);
+                }
+            }
+
+            self.check().await;
+            tokio::time::sleep(WATCH_INTERVAL).await;
+        }
+    }
+
+    async fn check(&mut self) {
+        let now = Utc::now().timestamp();
+        let heartbeats: Vec<Heartbeat> =
+            self.heartbeats.lock().unwrap().values().cloned().collect();
+
+        for heartbeat in heartbeats {
+            let silent = now - heartbeat.timestamp > self.timeout;
+
+            if silent && self.silent_rigs.insert(heartbeat.rig.clone()) {
+                self.alert_service
+                    .send(
+                        Severity::Critical,
+                        format!(
+                            "🚨 Rig *{}* hasn't reported since {}\\.",
+                            markdown::replace_specail_chars(&heartbeat.rig),
+                            markdown::replace_specail_chars(&timedate::diff_for_humans(
+                                heartbeat.timestamp,
+                                now
+                            ))
+                        ),
+                    )
+                    .await;
+            } else if !silent && self.silent_rigs.remove(&heartbeat.rig) {
+                self.alert_service
+                    .send(
+                        Severity::Info,
+                        format!(
+                            "✅ Rig *{}* is reporting again\\.",
+                            markdown::replace_specail_chars(&heartbeat.rig)
+                        ),
+                    )
+                    .await;
+            }
+
+            let supervisor_down = !silent && !heartbeat.supervisor_reachable;
+            if supervisor_down && self.supervisor_down_rigs.insert(heartbeat.rig.clone()) {
+                self.alert_service
+                    .send(
+                        Severity::Critical,
+                        format!(
+                            "🚨 Supervisor on rig *{}* is unreachable\\.",
+                            markdown::replace_specail_chars(&heartbeat.rig)
+                        ),
+                    )
+                    .await;
+            } else if !silent
+                && heartbeat.supervisor_reachable
+                && self.supervisor_down_rigs.remove(&heartbeat.rig)
+            {
+                self.alert_service
+                    .send(
+                        Severity::Info,
+                        format!(
+                            "✅ Supervisor on rig *{}* is reachable again\\.",
+                            markdown::replace_specail_chars(&heartbeat.rig)
+                        ),
+                    )
+                    .await;
+            }
+
+            let cycle_after = self
+                .plugs
+                .plug(&heartbeat.rig)
+                .and_then(|plug| plug.auto_cycle_after);
+            if let Some(cycle_after) = cycle_after {
+                // A fresh heartbeat ends the outage, whether or not the rig
+                // was silent long enough to be alerted about.
+                if now - heartbeat.timestamp <= cycle_after * 60 {
+                    self.cycled_rigs.remove(&heartbeat.rig);
+                } else if self.cycled_rigs.insert(heartbeat.rig.clone()) {
+                    self.power_cycle(&heartbeat.rig, cycle_after).await;
+                }
+            }
+        }
+    }
+
+    async fn power_cycle(&self, rig: &str, cycle_after: i64) {
+        let plugs = self.plugs.clone();
+        let rig_name = rig.to_string();
+        let result = tokio::task::spawn_blocking(move || {
+            plugs
+                .power(&rig_name, PlugAction::Cycle)
+                .map_err(|error| error.to_string())
+        })
+        .await
+        .unwrap_or_else(|error| Err(error.to_string()));
+
+        let text = match result {
+            Ok(_) => format!(
+                "🔌 Rig *{}* was unreachable for {} minutes and got power\\-cycled\\.",
+                markdown::replace_specail_chars(rig),
+                cycle_after
+            ),
+            Err(error) => {
+                log::error!("Error in power-cycle rig {}. message: {}", rig, error);
+                format!(
+                    "❌ Rig *{}* was unreachable for {} minutes, power\\-cycling it failed: {}",
+                    markdown::replace_specail_chars(rig),
+                    cycle_after,
+                    markdown::replace_specail_chars(&error)
+                )
+            }
+        };
+        self.alert_service.send(Severity::Critical, text).await;
+    }
+}
+
+pub fn rig_name() -> String {
+    env::var("RIG_NAME")
+        .ok()
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| "rig".to_string())
+}
+
+fn heartbeat_secret() -> Option<String> {
+    env::var("HEARTBEAT_SECRET")
+        .ok()
+        .filter(|value| !value.is_empty())
+}
+
+fn send_heartbeat(
+    target: &str,
+    secret: Option<&str>,
+    heartbeat: &Heartbeat,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if target.starts_with("http://") || target.starts_with("https://") {
+        let mut request = reqwest::blocking::Client::new()
+            .post(target)
+            .timeout(Duration::from_secs(10))
+            .json(heartbeat);
+        if let Some(secret) = secret {
+            request = request.header(SECRET_HEADER, secret);
+        }
+        request.send()?.error_for_status()?;
+    } else {
+        // Write to a temporary file first, so a watcher never reads half a heartbeat.
+        let directory = Path::new(target);
+        fs::create_dir_all(directory)?;
+        let temporary_path = directory.join(format!(".{}.json.tmp", heartbeat.rig));
+        fs::write(&temporary_path, serde_json::to_string(heartbeat)?)?;
+        fs::rename(
+            temporary_path,
+            directory.join(format!("{}.json", heartbeat.rig)),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn read_heartbeat_dir(directory: &Path) -> Vec<Heartbeat> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(error) => {
+            log::error!("Error in read heartbeat directory. message: {}", error);
+            return vec![];
+        }
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .filter_map(|path| {
+            let mut heartbeat: Heartbeat =
+                serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+            // Like the receive time of the listener, the write time of the file
+            // doesn't depend on the clock of the rig.
+            let modified = fs::metadata(&path).ok()?.modified().ok()?;
+            heartbeat.timestamp = DateTime::<Utc>::from(modified).timestamp();
+            Some(heartbeat)
+        })
+        .collect()
+}
+
+/// A minimal HTTP endpoint accepting `POST` requests with a JSON heartbeat body
+/// and the shared secret in the `X-Heartbeat-Secret` header. Every connection
+/// is handled on its own thread, so a slow client doesn't hold up the others.
+/// The receive time is stored instead of the reported one, so clock skew
+/// between rigs doesn't matter.
+fn listen(address: &str, secret: String, heartbeats: Heartbeats) -> std::io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    let secret = Arc::new(secret);
+    let connections = Arc::new(AtomicUsize::new(0));
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                log::error!("Error in accept heartbeat connection. message: {}", error);
+                continue;
+            }
+        };
+
+        if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            connections.fetch_sub(1, Ordering::SeqCst);
+            log::warn!("Too many heartbeat connections, dropping one");
+            continue;
+        }
+
+        let secret = secret.clone();
+        let heartbeats = heartbeats.clone();
+        let connections = connections.clone();
+        std::thread::spawn(move || {
+            handle_connection(stream, &secret, &heartbeats);
+            connections.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+
+    Ok(())
+}
+
+fn handle_connection(mut stream: TcpStream, secret: &str, heartbeats: &Heartbeats) {
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(10)));
+    let response = match read_heartbeat_request(&stream, secret) {
+        Ok(mut heartbeat) => {
+            heartbeat.timestamp = Utc::now().timestamp();
+            heartbeats
+                .lock()
+                .unwrap()
+                .insert(heartbeat.rig.clone(), heartbeat);
+            "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n"
+        }
+        Err(response) => response,
+    };
+    let _ = stream.write_all(response.as_bytes());
+}
+
+/// Reads the heartbeat of a request, or returns the response rejecting it.
+fn read_heartbeat_request(stream: &TcpStream, secret: &str) -> Result<Heartbeat, &'static str> {
+    const BAD_REQUEST: &str =
+        "HTTP/1.1 400 Bad Request\r\nConnection: close\r\nContent-Length: 0\r\n\r\n";
+    const UNAUTHORIZED: &str =
+        "HTTP/1.1 401 Unauthorized\r\nConnection: close\r\nContent-Length: 0\r\n\r\n";
+
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader
+        .read_line(&mut request_line)
+        .map_err(|_| BAD_REQUEST)?;
+    if !request_line.starts_with("POST ") {
+        return Err(BAD_REQUEST);
+    }
+
+    let mut content_length = 0;
+    let mut authorized = false;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).map_err(|_| BAD_REQUEST)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().map_err(|_| BAD_REQUEST)?;
+            } else if name.eq_ignore_ascii_case(SECRET_HEADER) {
+                authorized = constant_time_eq(value.trim().as_bytes(), secret.as_bytes());
+            }
+        }
+    }
+
+    if !authorized {
+        return Err(UNAUTHORIZED);
+    }
+    if content_length > MAX_BODY_SIZE {
+        return Err(BAD_REQUEST);
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(|_| BAD_REQUEST)?;
+    serde_json::from_slice(&body).map_err(|_| BAD_REQUEST)
+}
+
+/// Compares without stopping at the first difference, so the time taken
+/// doesn't tell how much of a guessed secret was right.
+fn constant_time_eq(first: &[u8], second: &[u8]) -> bool {
+    first.len() == second.len()
+        && first
+            .iter()
+            .zip(second)
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
@@ -0,0 +1,353 @@
+use crate::{supervisor::Process, utils::jsonl};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const CRASH_STATES: [&str; 3] = ["EXITED", "BACKOFF", "FATAL"];
+const RETENTION_SECS: i64 = 31 * 86400;
+/// How long a restart announced with `expect_restart` is waited for.
+const EXPECTED_RESTART_SECS: i64 = 600;
+/// Recorded for the time the bot couldn't see the program, either because it
+/// wasn't running or supervisord was unreachable. Left out of the uptime.
+pub const UNKNOWN: &str = "UNKNOWN";
+pub const AVAILABILITY_WINDOWS: [(&str, i64); 3] =
+    [("24h", 86400), ("7d", 7 * 86400), ("30d", 30 * 86400)];
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StateTransition {
+    pub program: String,
+    pub state: String,
+    pub timestamp: i64,
+    pub crash: bool,
+    #[serde(default)]
+    pub start_time: i64,
+    /// Caused by the bot itself, e.g. an automatic restart or a pool switch.
+    #[serde(default)]
+    pub intentional: bool,
+}
+
+#[derive(Debug)]
+pub struct StateHistory {
+    path: PathBuf,
+    checkpoint_path: PathBuf,
+    transitions: Vec<StateTransition>,
+    last_seen: HashMap<String, (String, Option<i64>)>,
+    last_poll: Option<i64>,
+    unknown_since: HashMap<String, i64>,
+    expected_restarts: HashMap<String, i64>,
+}
+
+impl StateHistory {
+    /// Loads the transitions stored by a previous run from `HISTORY_FILE`
+    /// (one JSON object per line), so availability survives a restart of the bot.
+    /// The time the bot was away counts as unknown from its last poll on.
+    pub fn new() -> Self {
+        let path = PathBuf::from(
+            env::var("HISTORY_FILE").unwrap_or_else(|_| "data/history.jsonl".to_string()),
+        );
+        let checkpoint_path = path.with_extension("checkpoint");
+
+        let transitions: Vec<StateTransition> = jsonl::read(&path);
+        let last_poll = fs::read_to_string(&checkpoint_path)
+            .ok()
+            .and_then(|content| content.trim().parse().ok());
+
+        let mut last_seen = HashMap::new();
+        for transition in transitions
+            .iter()
+            .filter(|transition| transition.state != UNKNOWN)
+        {
+            let start_time = Some(transition.start_time).filter(|start_time| *start_time > 0);
+            last_seen.insert(
+                transition.program.clone(),
+                (transition.state.clone(), start_time),
+            );
+        }
+
+        let mut history = StateHistory {
+            path,
+            checkpoint_path,
+            transitions,
+            last_seen,
+            last_poll,
+            unknown_since: HashMap::new(),
+            expected_restarts: HashMap::new(),
+        };
+        history.mark_unknown();
+        history
+    }
+
+    /// Compares the polled process list with the last seen states and stores
+    /// every change. A program that was restarted between two polls keeps its
+    /// `RUNNING` state but gets a new start time, so it is counted as a crash too,
+    /// unless the restart was announced with `expect_restart`. After an unknown
+    /// period, a program that kept its state and start time ran through it.
+    pub fn record(&mut self, process_list: &[Process], now: i64) -> Vec<StateTransition> {
+        let mut new_transitions = vec![];
+
+        for program in process_list {
+            let unknown_since = self.unknown_since.remove(&program.name);
+            let last_seen = self.last_seen.get(&program.name);
+            let changed = match last_seen {
+                None => true,
+                Some((state, _)) if state != &program.state => true,
+                Some((_, Some(start_time))) => {
+                    program.state == "RUNNING" && *start_time != program.start_time
+                }
+                Some((_, None)) => unknown_since.is_some(),
+            };
+
+            if changed {
+                let restarted = matches!(last_seen, Some((state, Some(start_time)))
+                    if state == "RUNNING"
+                        && program.state == "RUNNING"
+                        && *start_time != program.start_time);
+                let mut crash = restarted
+                    || (last_seen.is_some_and(|(state, _)| state == "RUNNING")
+                        && CRASH_STATES.contains(&program.state.as_str()));
+
+                let intentional = self
+                    .expected_restarts
+                    .remove(&program.name)
+                    .is_some_and(|since| now - since <= EXPECTED_RESTART_SECS)
+                    && !CRASH_STATES.contains(&program.state.as_str());
+                crash &= !intentional;
+
+                if let Some(since) = unknown_since {
+                    self.push_unknown(&program.name, since, &mut new_transitions);
+                }
+
+                let transition = StateTransition {
+                    program: program.name.clone(),
+                    state: program.state.clone(),
+                    timestamp: self.event_time(program, now),
+                    crash,
+                    start_time: program.start_time,
+                    intentional,
+                };
+                self.transitions.push(transition.clone());
+                new_transitions.push(transition);
+            }
+
+            self.last_seen.insert(
+                program.name.clone(),
+                (program.state.clone(), Some(program.start_time)),
+            );
+        }
+
+        if self.prune(now - RETENTION_SECS) {
+            jsonl::rewrite(&self.path, &self.transitions);
+        } else {
+            jsonl::append(&self.path, &new_transitions);
+        }
+
+        self.last_poll = Some(now);
+        if let Err(error) = fs::write(&self.checkpoint_path, now.to_string()) {
+            log::error!(
+                "Error in write {}. message: {}",
+                self.checkpoint_path.display(),
+                error
+            );
+        }
+
+        new_transitions
+    }
+
+    /// Marks the state of every known program as unknown from the last
+    /// successful poll on, for when supervisord can't be reached.
+    pub fn mark_unknown(&mut self) {
+        let Some(last_poll) = self.last_poll else {
+            return;
+        };
+        for program in self.last_seen.keys() {
+            self.unknown_since
+                .entry(program.clone())
+                .or_insert(last_poll);
+        }
+    }
+
+    /// Announces that the bot is about to restart or stop the program, so its
+    /// next transition isn't counted as a crash.
+    pub fn expect_restart(&mut self, program: &str, now: i64) {
+        self.expected_restarts.insert(program.to_string(), now);
+    }
+
+    fn push_unknown(
+        &mut self,
+        program: &str,
+        since: i64,
+        new_transitions: &mut Vec<StateTransition>,
+    ) {
+        let last = self
+            .transitions
+            .iter()
+            .rev()
+            .find(|transition| transition.program == program);
+        if last.is_some_and(|transition| transition.state == UNKNOWN) {
+            return;
+        }
+
+        let transition = StateTransition {
+            program: program.to_string(),
+            state: UNKNOWN.to_string(),
+            timestamp: last.map_or(since, |transition| since.max(transition.timestamp)),
+            crash: false,
+            start_time: 0,
+            intentional: false,
+        };
+        self.transitions.push(transition.clone());
+        new_transitions.push(transition);
+    }
+
+    /// Percentage of the known time in `[from, to]` that the program spent `RUNNING`.
+    /// Returns `None` when nothing is known about the program in that window.
+    pub fn uptime_percent(&self, program: &str, from: i64, to: i64) -> Option<f64> {
+        let segments = self.segments(program, from, to);
+        let known_secs: i64 = segments
+            .iter()
+            .filter(|(state, _, _)| *state != UNKNOWN)
+            .map(|(_, start, end)| end - start)
+            .sum();
+        let running_secs: i64 = segments
+            .iter()
+            .filter(|(state, _, _)| *state == "RUNNING")
+            .map(|(_, start, end)| end - start)
+            .sum();
+
+        if known_secs == 0 {
+            None
+        } else {
+            Some(running_secs as f64 * 100.0 / known_secs as f64)
+        }
+    }
+
+    /// Seconds in `[from, to]` the program is known to have spent `RUNNING`.
+    pub fn running_secs(&self, program: &str, from: i64, to: i64) -> i64 {
+        self.segments(program, from, to)
+            .iter()
+            .filter(|(state, _, _)| *state == "RUNNING")
+            .map(|(_, start, end)| end - start)
+            .sum()
+    }
+
+    /// The periods in `[from, to]` in which the program was known not to be `RUNNING`.
+    pub fn downtime(&self, program: &str, from: i64, to: i64) -> Vec<(i64, i64)> {
+        let mut periods: Vec<(i64, i64)> = vec![];
+        for (state, start, end) in self.segments(program, from, to) {
+            if state == "RUNNING" || state == UNKNOWN {
+                continue;
+            }
+            match periods.last_mut() {
+                Some(last) if last.1 == start => last.1 = end,
+                _ => periods.push((start, end)),
+            }
+        }
+        periods
+    }
+
+    /// Splits `[from, to]` into `(state, start, end)` segments, leaving out the
+    /// time before the first known transition.
+    fn segments(&self, program: &str, from: i64, to: i64) -> Vec<(&str, i64, i64)> {
+        let mut segments = vec![];
+        let mut current: Option<(&str, i64)> = None;
+
+        let boundaries = self
+            .transitions
+            .iter()
+            .filter(|transition| transition.program == program)
+            .map(|transition| (transition.state.as_str(), transition.timestamp))
+            .chain(
+                self.unknown_since
+                    .get(program)
+                    .map(|since| (UNKNOWN, *since)),
+            )
+            .chain(std::iter::once(("", to)));
+
+        for (state, timestamp) in boundaries {
+            if let Some((current_state, since)) = current {
+                let start = since.max(from);
+                let end = timestamp.min(to);
+                if end > start {
+                    segments.push((current_state, start, end));
+                }
+            }
+            current = Some((state, timestamp));
+        }
+
+        segments
+    }
+
+    /// Uptime percentage of the program for every window in `AVAILABILITY_WINDOWS`.
+    pub fn availability(&self, program: &str, now: i64) -> Vec<(&'static str, Option<f64>)> {
+        AVAILABILITY_WINDOWS
+            .iter()
+            .map(|(label, secs)| (*label, self.uptime_percent(program, now - secs, now)))
+            .collect()
+    }
+
+    pub fn crash_count(&self, program: &str, from: i64, to: i64) -> usize {
+        self.transitions
+            .iter()
+            .filter(|transition| {
+                transition.program == program
+                    && transition.crash
+                    && transition.timestamp >= from
+                    && transition.timestamp <= to
+            })
+            .count()
+    }
+
+    /// Uses the timestamps reported by supervisord when they are plausible,
+    /// so a change is not shifted by the polling interval.
+    fn event_time(&self, program: &Process, now: i64) -> i64 {
+        let reported = if program.state == "RUNNING" {
+            program.start_time
+        } else {
+            program.stop_time
+        };
+
+        let last_timestamp = self
+            .transitions
+            .iter()
+            .rev()
+            .find(|transition| transition.program == program.name)
+            .map(|transition| transition.timestamp);
+
+        match last_timestamp {
+            _ if reported <= 0 || reported > now => now,
+            Some(last_timestamp) if reported <= last_timestamp => now,
+            _ => reported,
+        }
+    }
+
+    /// Drops transitions older than `cutoff`, keeping the latest one of each
+    /// program so the state at the beginning of a window is still known.
+    /// Returns whether anything was removed.
+    fn prune(&mut self, cutoff: i64) -> bool {
+        let mut latest_before_cutoff: HashMap<&str, usize> = HashMap::new();
+        for (index, transition) in self.transitions.iter().enumerate() {
+            if transition.timestamp < cutoff {
+                latest_before_cutoff.insert(&transition.program, index);
+            }
+        }
+
+        let keep: Vec<usize> = latest_before_cutoff.into_values().collect();
+        let count = self.transitions.len();
+        let mut index = 0;
+        self.transitions.retain(|transition| {
+            let retained = transition.timestamp >= cutoff || keep.contains(&index);
+            index += 1;
+            retained
+        });
+
+        self.transitions.len() != count
+    }
+}
+
+impl Default for StateHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
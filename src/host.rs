@@ -0,0 +1,346 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crate::utils::timedate;
+
+/// How long CPU usage is measured over, as `/proc/stat` only has totals since boot.
+const CPU_SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Default)]
+pub struct HostMetrics {
+    pub cpu_usage: Option<f64>,
+    pub load_average: Option<(f64, f64, f64)>,
+    /// Used and total memory in kB, not counting reclaimable caches as used.
+    pub memory: Option<(u64, u64)>,
+    /// Used and total swap in kB.
+    pub swap: Option<(u64, u64)>,
+    pub uptime: Option<i64>,
+    pub temperatures: Vec<(String, f64)>,
+}
+
+impl HostMetrics {
+    /// Reads the metrics from `/proc`, leaving out whatever can't be read. Blocks
+    /// for a moment to measure CPU usage.
+    pub fn read() -> Self {
+        let memory_info = fs::read_to_string("/proc/meminfo").unwrap_or_default();
+        let kilobytes = |key: &str| meminfo_value(&memory_info, key);
+
+        HostMetrics {
+            cpu_usage: cpu_usage().ok(),
+            load_average: load_average().ok(),
+            memory: kilobytes("MemTotal")
+                .zip(kilobytes("MemAvailable"))
+                .map(|(total, available)| (total.saturating_sub(available), total)),
+            swap: kilobytes("SwapTotal")
+                .zip(kilobytes("SwapFree"))
+                .map(|(total, free)| (total.saturating_sub(free), total)),
+            uptime: fs::read_to_string("/proc/uptime").ok().and_then(|content| {
+                content
+                    .split_whitespace()
+                    .next()
+                    .and_then(|seconds| seconds.parse::<f64>().ok())
+                    .map(|seconds| seconds as i64)
+            }),
+            temperatures: read_temperatures(),
+        }
+    }
+
+    /// Label and value of every metric that could be read.
+    pub fn format(&self) -> Vec<(&'static str, String)> {
+        let mut lines = vec![];
+
+        if let Some(cpu_usage) = self.cpu_usage {
+            lines.push(("cpu", format!("{:.1}%", cpu_usage)));
+        }
+        if let Some((one, five, fifteen)) = self.load_average {
+            lines.push(("load", format!("{:.2} {:.2} {:.2}", one, five, fifteen)));
+        }
+        if let Some((used, total)) = self.memory {
+            lines.push(("memory", format_usage(used, total)));
+        }
+        if let Some((used, total)) = self.swap.filter(|(_, total)| *total > 0) {
+            lines.push(("swap", format_usage(used, total)));
+        }
+        if let Some(uptime) = self.uptime {
+            lines.push(("host uptime", format_uptime(uptime)));
+        }
+        if !self.temperatures.is_empty() {
+            let temperatures = self
+                .temperatures
+                .iter()
+                .map(|(sensor, temperature)| format!("{} {:.0}°C", sensor, temperature))
+                .collect::<Vec<String>>();
+            lines.push(("temperatures", temperatures.join(", ")));
+        }
+
+        lines
+    }
+}
+
+/// Temperatures in °C of every hwmon sensor and thermal zone, labelled by
+/// sensor. Thermal zones also exposed through hwmon are only listed once.
+pub fn read_temperatures() -> Vec<(String, f64)> {
+    let mut temperatures = vec![];
+    let read_celsius = |path: &Path| {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|value| value.trim().parse::<f64>().ok())
+            .map(|millidegrees| millidegrees / 1000.0)
+    };
+    let read_trimmed = |path: PathBuf| {
+        fs::read_to_string(path)
+            .ok()
+            .map(|value| value.trim().to_string())
+    };
+
+    for hwmon in sorted_entries("/sys/class/hwmon") {
+        let name = read_trimmed(hwmon.join("name")).unwrap_or_else(|| "hwmon".to_string());
+        for input in sorted_entries(&hwmon.to_string_lossy()) {
+            let file_name = input
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            let sensor = match file_name
+                .strip_prefix("temp")
+                .and_then(|rest| rest.strip_suffix("_input"))
+            {
+                Some(sensor) => sensor.to_string(),
+                None => continue,
+            };
+            if let Some(temperature) = read_celsius(&input) {
+                let label = read_trimmed(hwmon.join(format!("temp{}_label", sensor)))
+                    .unwrap_or_else(|| format!("temp{}", sensor));
+                temperatures.push((format!("{} {}", name, label), temperature));
+            }
+        }
+    }
+
+    for zone in sorted_entries("/sys/class/thermal") {
+        if !zone.to_string_lossy().contains("thermal_zone") {
+            continue;
+        }
+        let zone_type = read_trimmed(zone.join("type")).unwrap_or_else(|| "thermal".to_string());
+        let covered = temperatures
+            .iter()
+            .any(|(sensor, _)| sensor.split(' ').next() == Some(zone_type.as_str()));
+        if covered {
+            continue;
+        }
+        if let Some(temperature) = read_celsius(&zone.join("temp")) {
+            temperatures.push((zone_type, temperature));
+        }
+    }
+
+    temperatures
+}
+
+fn sorted_entries(directory: &str) -> Vec<PathBuf> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(directory)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .collect()
+        })
+        .unwrap_or_default();
+    entries.sort();
+    entries
+}
+
+/// Resource usage of a process together with all its descendants, so miners
+/// started through a wrapper script are covered too.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessMetrics {
+    /// Percentage of one core, like `top` shows it.
+    pub cpu_usage: f64,
+    pub rss_kb: u64,
+    pub threads: u64,
+    pub children: usize,
+}
+
+impl ProcessMetrics {
+    /// Reads the metrics of the process tree under `pid`, `None` when the process
+    /// doesn't exist. Blocks for a moment to measure CPU usage.
+    pub fn read(pid: i32) -> Option<Self> {
+        if pid <= 0 {
+            return None;
+        }
+
+        let tree = process_tree(pid);
+        let (_, total_before) = cpu_times().ok()?;
+        let jiffies_before: u64 = tree.iter().filter_map(|pid| process_jiffies(*pid)).sum();
+        process_jiffies(pid)?;
+
+        thread::sleep(CPU_SAMPLE_INTERVAL);
+
+        let (_, total_after) = cpu_times().ok()?;
+        let jiffies_after: u64 = tree.iter().filter_map(|pid| process_jiffies(*pid)).sum();
+        let total = total_after.saturating_sub(total_before).max(1);
+
+        let mut metrics = ProcessMetrics {
+            cpu_usage: jiffies_after.saturating_sub(jiffies_before) as f64 * 100.0 / total as f64
+                * cpu_count() as f64,
+            children: tree.len() - 1,
+            ..ProcessMetrics::default()
+        };
+        for pid in &tree {
+            let status = fs::read_to_string(format!("/proc/{}/status", pid)).unwrap_or_default();
+            metrics.rss_kb += meminfo_value(&status, "VmRSS").unwrap_or_default();
+            metrics.threads += meminfo_value(&status, "Threads").unwrap_or_default();
+        }
+
+        Some(metrics)
+    }
+
+    pub fn format(&self) -> String {
+        format!(
+            "cpu {:.1}% | rss {:.1} MiB | {} threads | {} {}",
+            self.cpu_usage,
+            self.rss_kb as f64 / 1024.0,
+            self.threads,
+            self.children,
+            if self.children == 1 {
+                "child"
+            } else {
+                "children"
+            }
+        )
+    }
+}
+
+/// The process and all its descendants.
+fn process_tree(pid: i32) -> Vec<i32> {
+    let parents: Vec<(i32, i32)> = fs::read_dir("/proc")
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<i32>().ok())
+                .filter_map(|pid| Some((pid, stat_fields(pid)?.get(1)?.parse().ok()?)))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut tree = vec![pid];
+    let mut index = 0;
+    while index < tree.len() {
+        let parent = tree[index];
+        tree.extend(
+            parents
+                .iter()
+                .filter(|(_, ppid)| *ppid == parent)
+                .map(|(pid, _)| *pid),
+        );
+        index += 1;
+    }
+    tree
+}
+
+/// User and system jiffies the process has used.
+fn process_jiffies(pid: i32) -> Option<u64> {
+    let fields = stat_fields(pid)?;
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some(utime + stime)
+}
+
+/// The fields of `/proc/<pid>/stat` after the command name, starting with the state.
+fn stat_fields(pid: i32) -> Option<Vec<String>> {
+    let content = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name is in parentheses and may itself contain spaces or parentheses.
+    let (_, rest) = content.rsplit_once(')')?;
+    Some(rest.split_whitespace().map(String::from).collect())
+}
+
+fn cpu_count() -> usize {
+    fs::read_to_string("/proc/stat")
+        .map(|content| {
+            content
+                .lines()
+                .filter(|line| {
+                    line.starts_with("cpu")
+                        && line.as_bytes().get(3).is_some_and(u8::is_ascii_digit)
+                })
+                .count()
+        })
+        .unwrap_or(1)
+        .max(1)
+}
+
+fn cpu_usage() -> io::Result<f64> {
+    let (busy_before, total_before) = cpu_times()?;
+    thread::sleep(CPU_SAMPLE_INTERVAL);
+    let (busy_after, total_after) = cpu_times()?;
+
+    let total = total_after.saturating_sub(total_before);
+    if total == 0 {
+        return Ok(0.0);
+    }
+    Ok(busy_after.saturating_sub(busy_before) as f64 * 100.0 / total as f64)
+}
+
+/// Busy and total jiffies of all CPUs from the `cpu` line of `/proc/stat`.
+fn cpu_times() -> io::Result<(u64, u64)> {
+    let content = fs::read_to_string("/proc/stat")?;
+    let values: Vec<u64> = content
+        .lines()
+        .find(|line| line.starts_with("cpu "))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no cpu line"))?
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|value| value.parse().ok())
+        .collect();
+
+    // user nice system idle iowait irq softirq steal; guest time is already in user.
+    let total: u64 = values.iter().take(8).sum();
+    let idle =
+        values.get(3).copied().unwrap_or_default() + values.get(4).copied().unwrap_or_default();
+    Ok((total.saturating_sub(idle), total))
+}
+
+fn load_average() -> io::Result<(f64, f64, f64)> {
+    let content = fs::read_to_string("/proc/loadavg")?;
+    let values: Vec<f64> = content
+        .split_whitespace()
+        .take(3)
+        .filter_map(|value| value.parse().ok())
+        .collect();
+
+    match values[..] {
+        [one, five, fifteen] => Ok((one, five, fifteen)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid loadavg",
+        )),
+    }
+}
+
+pub fn meminfo_value(content: &str, key: &str) -> Option<u64> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+        .and_then(|value| value.split_whitespace().next())
+        .and_then(|value| value.parse().ok())
+}
+
+fn format_usage(used_kb: u64, total_kb: u64) -> String {
+    let gigabytes = |kilobytes: u64| kilobytes as f64 / 1024.0 / 1024.0;
+    format!(
+        "{:.1} / {:.1} GiB ({:.0}%)",
+        gigabytes(used_kb),
+        gigabytes(total_kb),
+        used_kb as f64 * 100.0 / total_kb.max(1) as f64
+    )
+}
+
+fn format_uptime(seconds: i64) -> String {
+    let days = seconds / 86400;
+    let rest = timedate::format_duration(seconds % 86400);
+
+    match days {
+        0 => rest,
+        1 => format!("1 day, {}", rest),
+        _ => format!("{} days, {}", days, rest),
+    }
+}
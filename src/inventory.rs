@@ -0,0 +1,197 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::host;
+
+/// 2 MB huge pages RandomX needs besides one per mining thread: 1040 for the
+/// 2080 MB dataset and 128 for the 256 MB cache.
+const RANDOMX_HUGEPAGES: u64 = 1168;
+
+/// 1 GB pages that hold the whole RandomX dataset, replacing most 2 MB pages.
+const RANDOMX_GIGAPAGES: u64 = 3;
+
+const GIGAPAGES_DIR: &str = "/sys/kernel/mm/hugepages/hugepages-1048576kB";
+
+/// Hardware and kernel facts of the rig that decide how fast it can mine.
+#[derive(Debug, Clone, Default)]
+pub struct Inventory {
+    pub cpu_model: Option<String>,
+    pub cores: usize,
+    pub threads: usize,
+    pub cache: Option<String>,
+    /// CPU flags, empty on architectures other than x86.
+    pub flags: Vec<String>,
+    pub memory_kb: Option<u64>,
+    pub hugepages_total: u64,
+    pub hugepages_free: u64,
+    pub hugepage_size_kb: u64,
+    /// 1 GB pages, `None` when the kernel or CPU doesn't support them.
+    pub gigapages: Option<(u64, u64)>,
+    pub kernel: Option<String>,
+    /// Whether `/dev/cpu/*/msr` is available, which xmrig's MSR mod needs.
+    pub msr: bool,
+}
+
+impl Inventory {
+    pub fn read() -> Self {
+        let cpu_info = fs::read_to_string("/proc/cpuinfo").unwrap_or_default();
+        let memory_info = fs::read_to_string("/proc/meminfo").unwrap_or_default();
+        let kilobytes = |key: &str| host::meminfo_value(&memory_info, key);
+        let read_number = |path: &str| {
+            fs::read_to_string(path)
+                .ok()
+                .and_then(|value| value.trim().parse::<u64>().ok())
+        };
+
+        let cpu_value = |key: &str| cpuinfo_value(&cpu_info, key);
+
+        let mut threads = 0;
+        let mut cores = HashSet::new();
+        for block in cpu_info.split("\n\n") {
+            let value = |key: &str| cpuinfo_value(block, key);
+            if value("processor").is_none() {
+                continue;
+            }
+            threads += 1;
+            if let (Some(physical_id), Some(core_id)) = (value("physical id"), value("core id")) {
+                cores.insert((physical_id, core_id));
+            }
+        }
+
+        Inventory {
+            cpu_model: cpu_value("model name").or_else(|| cpu_value("Model")),
+            cores: if cores.is_empty() {
+                threads
+            } else {
+                cores.len()
+            },
+            threads,
+            cache: cpu_value("cache size"),
+            flags: cpu_value("flags")
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(String::from)
+                .collect(),
+            memory_kb: kilobytes("MemTotal"),
+            hugepages_total: kilobytes("HugePages_Total").unwrap_or_default(),
+            hugepages_free: kilobytes("HugePages_Free").unwrap_or_default(),
+            hugepage_size_kb: kilobytes("Hugepagesize").unwrap_or_default(),
+            gigapages: read_number(&format!("{}/nr_hugepages", GIGAPAGES_DIR))
+                .zip(read_number(&format!("{}/free_hugepages", GIGAPAGES_DIR))),
+            kernel: fs::read_to_string("/proc/sys/kernel/osrelease")
+                .ok()
+                .map(|value| value.trim().to_string()),
+            msr: Path::new("/dev/cpu/0/msr").exists()
+                || fs::read_to_string("/proc/modules")
+                    .unwrap_or_default()
+                    .lines()
+                    .any(|line| line.starts_with("msr ")),
+        }
+    }
+
+    fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|value| value == flag)
+    }
+
+    /// Prerequisites for fast RandomX mining that are missing on this rig.
+    pub fn randomx_issues(&self) -> Vec<String> {
+        let mut issues = vec![];
+        let x86 = !self.flags.is_empty();
+
+        if x86 && !self.has_flag("aes") {
+            issues.push("the CPU has no AES-NI".to_string());
+        }
+
+        let needed = RANDOMX_HUGEPAGES + self.threads as u64;
+        let gigapages = self.gigapages.map(|(total, _)| total).unwrap_or_default();
+        if self.hugepage_size_kb == 2048
+            && self.hugepages_total < needed
+            && gigapages < RANDOMX_GIGAPAGES
+        {
+            issues.push(format!(
+                "only {} of the {} huge pages needed are configured (vm.nr_hugepages)",
+                self.hugepages_total, needed
+            ));
+        }
+
+        if x86 && !self.msr {
+            issues.push("the msr module isn't loaded (modprobe msr)".to_string());
+        }
+
+        issues
+    }
+
+    /// Label and value of every fact that could be read.
+    pub fn format(&self) -> Vec<(&'static str, String)> {
+        let mut lines = vec![];
+
+        if let Some(cpu_model) = &self.cpu_model {
+            lines.push(("cpu", cpu_model.clone()));
+        }
+        lines.push((
+            "cores",
+            format!(
+                "{} {}, {} {}",
+                self.cores,
+                if self.cores == 1 { "core" } else { "cores" },
+                self.threads,
+                if self.threads == 1 {
+                    "thread"
+                } else {
+                    "threads"
+                }
+            ),
+        ));
+        if let Some(cache) = &self.cache {
+            lines.push(("cache", cache.clone()));
+        }
+        if !self.flags.is_empty() {
+            let flags = ["aes", "avx2", "avx512f"]
+                .iter()
+                .map(|flag| format!("{} {}", flag, if self.has_flag(flag) { "✅" } else { "❌" }))
+                .collect::<Vec<String>>();
+            lines.push(("flags", flags.join(", ")));
+        }
+        if let Some(memory_kb) = self.memory_kb {
+            lines.push((
+                "memory",
+                format!("{:.1} GiB", memory_kb as f64 / 1024.0 / 1024.0),
+            ));
+        }
+        lines.push((
+            "huge pages",
+            format!(
+                "{} total, {} free ({} kB)",
+                self.hugepages_total, self.hugepages_free, self.hugepage_size_kb
+            ),
+        ));
+        lines.push((
+            "1GB pages",
+            match self.gigapages {
+                Some((total, free)) => format!("{} total, {} free", total, free),
+                None => "not supported".to_string(),
+            },
+        ));
+        if let Some(kernel) = &self.kernel {
+            lines.push(("kernel", kernel.clone()));
+        }
+        if !self.flags.is_empty() {
+            lines.push((
+                "msr",
+                if self.msr { "available" } else { "not loaded" }.to_string(),
+            ));
+        }
+
+        lines
+    }
+}
+
+/// The first value of `key` in `/proc/cpuinfo` content, which lists one block
+/// of `key : value` lines per processor.
+fn cpuinfo_value(content: &str, key: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name.trim() == key).then(|| value.trim().to_string())
+    })
+}
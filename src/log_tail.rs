@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use crate::{
+    config::LogStream,
+    supervisor::{Process, SupervisorService},
+};
+
+const TAIL_LENGTH: i64 = 64 * 1024;
+
+#[derive(Debug, Default)]
+struct TailState {
+    offset: i64,
+    partial_line: String,
+}
+
+/// Follows the stdout/stderr logs of supervisor programs through
+/// `tailProcess*Log` and hands out only the complete lines written since the
+/// previous call.
+#[derive(Debug, Default)]
+pub struct LogTailer {
+    states: HashMap<(String, LogStream), TailState>,
+}
+
+impl LogTailer {
+    pub fn new() -> Self {
+        LogTailer::default()
+    }
+
+    /// The first call for a program only remembers the current log size, so old
+    /// output is never reported as new.
+    pub fn new_lines(
+        &mut self,
+        supervisor_service: &SupervisorService,
+        process: &Process,
+        stream: LogStream,
+    ) -> Vec<String> {
+        let key = (process.name.clone(), stream);
+        let first_call = !self.states.contains_key(&key);
+        let state = self.states.entry(key).or_default();
+
+        let length = if first_call { 0 } else { TAIL_LENGTH };
+        let (data, offset, overflow) =
+            match supervisor_service.tail_process_log(process, stream, state.offset, length) {
+                Ok(result) => result,
+                Err(error) => {
+                    log::error!("Error in tail log of {}. message: {}", process.name, error);
+                    return vec![];
+                }
+            };
+
+        // supervisord always returns the last `length` bytes of the log, which may
+        // start before our offset; keep only the part we haven't seen yet. A log
+        // that got smaller was rotated or cleared, so all of it is new.
+        let data = data.as_bytes();
+        let new_bytes = if offset < state.offset {
+            data.len()
+        } else {
+            ((offset - state.offset) as usize).min(data.len())
+        };
+        if overflow {
+            state.partial_line.clear();
+        }
+
+        let chunk = String::from_utf8_lossy(&data[data.len() - new_bytes..]);
+        state.offset = offset;
+        state.partial_line.push_str(&chunk);
+
+        let mut lines: Vec<String> = state.partial_line.split('\n').map(String::from).collect();
+        state.partial_line = lines.pop().unwrap_or_default();
+
+        lines
+            .into_iter()
+            .map(|line| line.trim_end_matches('\r').to_string())
+            .filter(|line| !line.is_empty())
+            .collect()
+    }
+}
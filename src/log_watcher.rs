@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+
+use crate::config::{Config, LogRule, LogStream};
+
+#[derive(Debug, Clone)]
+pub struct LogMatch {
+    pub program: String,
+    pub rule: LogRule,
+    pub line: String,
+    /// Matches of the same rule swallowed by its cooldown since the last alert.
+    pub suppressed: usize,
+}
+
+/// Evaluates the `log_rules` of every program over newly written log lines.
+pub struct LogWatcher {
+    rules: HashMap<String, Vec<(LogRule, Regex)>>,
+    last_fired: HashMap<(String, usize), i64>,
+    suppressed: HashMap<(String, usize), usize>,
+}
+
+impl LogWatcher {
+    pub fn new(config: &Config) -> Self {
+        let rules = config
+            .programs
+            .iter()
+            .map(|(program, program_config)| {
+                let rules = program_config
+                    .log_rules
+                    .iter()
+                    .filter_map(|rule| match Regex::new(&rule.pattern) {
+                        Ok(regex) => Some((rule.clone(), regex)),
+                        Err(error) => {
+                            log::error!(
+                                "Invalid log rule {} of {}. message: {}",
+                                rule.pattern,
+                                program,
+                                error
+                            );
+                            None
+                        }
+                    })
+                    .collect();
+                (program.clone(), rules)
+            })
+            .collect();
+
+        LogWatcher {
+            rules,
+            last_fired: HashMap::new(),
+            suppressed: HashMap::new(),
+        }
+    }
+
+    /// The log streams that have to be tailed for the program's rules.
+    pub fn streams(&self, program: &str) -> Vec<LogStream> {
+        let rules = match self.rules.get(program) {
+            Some(rules) => rules,
+            None => return vec![],
+        };
+
+        [LogStream::Stdout, LogStream::Stderr]
+            .into_iter()
+            .filter(|stream| rules.iter().any(|(rule, _)| rule.stream.covers(*stream)))
+            .collect()
+    }
+
+    pub fn check(
+        &mut self,
+        program: &str,
+        stream: LogStream,
+        lines: &[String],
+        now: i64,
+    ) -> Vec<LogMatch> {
+        let rules = match self.rules.get(program) {
+            Some(rules) => rules,
+            None => return vec![],
+        };
+
+        let mut matches = vec![];
+        for (index, (rule, regex)) in rules.iter().enumerate() {
+            if !rule.stream.covers(stream) {
+                continue;
+            }
+
+            for line in lines.iter().filter(|line| regex.is_match(line)) {
+                let key = (program.to_string(), index);
+                let cooling_down = self
+                    .last_fired
+                    .get(&key)
+                    .is_some_and(|last_fired| now - last_fired < rule.cooldown);
+
+                if cooling_down {
+                    *self.suppressed.entry(key).or_default() += 1;
+                    continue;
+                }
+
+                matches.push(LogMatch {
+                    program: program.to_string(),
+                    rule: rule.clone(),
+                    line: line.clone(),
+                    suppressed: self.suppressed.remove(&key).unwrap_or_default(),
+                });
+                self.last_fired.insert(key, now);
+            }
+        }
+
+        matches
+    }
+}
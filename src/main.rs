@@ -1,15 +1,43 @@
 use dotenv::dotenv;
+pub mod alert;
 pub mod bot;
 pub mod bot_handler;
+pub mod chart;
+pub mod config;
+pub mod disk;
+pub mod earnings;
+pub mod heartbeat;
+pub mod history;
+pub mod host;
+pub mod inventory;
+pub mod log_tail;
+pub mod log_watcher;
+pub mod maintenance;
+pub mod miners;
+pub mod monitor;
+pub mod plug;
+pub mod pools;
+pub mod power;
+pub mod probe;
+pub mod profit;
+pub mod report;
+pub mod samples;
+pub mod stratum;
 pub mod supervisor;
+pub mod thermal;
 pub mod utils;
 use bot::TelegramBotService;
+use config::Config;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     utils::logger::initilize()?;
     dotenv().ok();
-    let telegram_bot_service = TelegramBotService::new();
+    let config = Config::load().map_err(|error| {
+        log::error!("Error in load config. message: {}", error);
+        error
+    })?;
+    let telegram_bot_service = TelegramBotService::new(config);
     let _ = telegram_bot_service.initialize().await;
 
     Ok(())
@@ -0,0 +1,162 @@
+use std::error::Error;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+use tokio::task::JoinHandle;
+
+use crate::config::{Config, MaintenanceCommand};
+
+/// Telegram messages are limited to 4096 characters, so only the end of long
+/// output is kept.
+const MAX_OUTPUT_LENGTH: usize = 3000;
+
+/// How long output is still read after the command exited.
+const OUTPUT_GRACE: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone)]
+pub struct CommandOutput {
+    /// Exit code, `None` when the command was killed by a signal.
+    pub status: Option<i32>,
+    pub success: bool,
+    /// Whether the command was killed for running into its timeout.
+    pub timed_out: bool,
+    /// Stdout followed by stderr, cut to the last `MAX_OUTPUT_LENGTH` characters.
+    pub output: String,
+    pub elapsed: Duration,
+}
+
+/// Runs the commands of the `maintenance` allow-list, looked up by name so
+/// nothing else can be run through the bot.
+#[derive(Clone)]
+pub struct MaintenanceService {
+    commands: Vec<MaintenanceCommand>,
+}
+
+impl MaintenanceService {
+    pub fn new(config: &Config) -> Self {
+        MaintenanceService {
+            commands: config
+                .maintenance
+                .iter()
+                .filter(|command| !command.command.is_empty())
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn commands(&self) -> &[MaintenanceCommand] {
+        &self.commands
+    }
+
+    pub async fn run(&self, name: &str) -> Result<CommandOutput, Box<dyn Error + Send + Sync>> {
+        let command = self
+            .commands
+            .iter()
+            .find(|command| command.name == name)
+            .ok_or_else(|| format!("{} is not an allowed maintenance command", name))?;
+
+        log::info!(
+            "Running maintenance command {}: {:?}",
+            name,
+            command.command
+        );
+
+        let started = Instant::now();
+        let mut child = Command::new(&command.command[0])
+            .args(&command.command[1..])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        // The pipes are read as the command goes, so the output up to a
+        // timeout is still there.
+        let stdout = Arc::new(Mutex::new(vec![]));
+        let stderr = Arc::new(Mutex::new(vec![]));
+        let readers = [
+            read_into(child.stdout.take(), stdout.clone()),
+            read_into(child.stderr.take(), stderr.clone()),
+        ];
+
+        let timeout = Duration::from_secs(command.timeout);
+        let status = match tokio::time::timeout(timeout, child.wait()).await {
+            Ok(status) => {
+                let status = status?;
+                // A background process started by the command can keep the
+                // pipes open, so the rest of the output is only waited for
+                // briefly.
+                let deadline = tokio::time::Instant::now() + OUTPUT_GRACE;
+                for mut reader in readers {
+                    if tokio::time::timeout_at(deadline, &mut reader)
+                        .await
+                        .is_err()
+                    {
+                        reader.abort();
+                    }
+                }
+                Some(status)
+            }
+            Err(_) => {
+                log::warn!(
+                    "Maintenance command {} killed after {}s",
+                    name,
+                    command.timeout
+                );
+                let _ = child.kill().await;
+                for reader in readers {
+                    reader.abort();
+                }
+                None
+            }
+        };
+
+        let mut text = String::from_utf8_lossy(&stdout.lock().unwrap()).to_string();
+        text.push_str(&String::from_utf8_lossy(&stderr.lock().unwrap()));
+        let length = text.chars().count();
+        if length > MAX_OUTPUT_LENGTH {
+            text = text.chars().skip(length - MAX_OUTPUT_LENGTH).collect();
+        }
+
+        let result = CommandOutput {
+            status: status.and_then(|status| status.code()),
+            success: status.is_some_and(|status| status.success()),
+            timed_out: status.is_none(),
+            output: text.trim_end().to_string(),
+            elapsed: started.elapsed(),
+        };
+        log::info!(
+            "Maintenance command {} exited with {:?} after {:.1}s",
+            name,
+            result.status,
+            result.elapsed.as_secs_f64()
+        );
+        Ok(result)
+    }
+}
+
+fn read_into<R>(pipe: Option<R>, buffer: Arc<Mutex<Vec<u8>>>) -> JoinHandle<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let Some(mut pipe) = pipe else {
+            return;
+        };
+        let mut chunk = [0; 4096];
+        while let Ok(count) = pipe.read(&mut chunk).await {
+            if count == 0 {
+                break;
+            }
+            let mut buffer = buffer.lock().unwrap();
+            buffer.extend_from_slice(&chunk[..count]);
+            // A character takes up to 4 bytes, so this still holds the last
+            // `MAX_OUTPUT_LENGTH` characters.
+            let excess = buffer.len().saturating_sub(MAX_OUTPUT_LENGTH * 4);
+            buffer.drain(..excess);
+        }
+    })
+}
@@ -0,0 +1,173 @@
+use std::error::Error;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use chrono::Utc;
+use serde_json::{Map, Value};
+
+use crate::miners::{DeviceStats, Hashrate, MinerStats, PoolStatus};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn fetch(host: &str, port: u16) -> Result<MinerStats, Box<dyn Error + Send + Sync>> {
+    let summary = command(host, port, "summary")?;
+    let summary = first_section(&summary, "SUMMARY");
+
+    // Not every miner of the family implements all commands, so only the
+    // summary is required.
+    let devs = command(host, port, "devs").unwrap_or_default();
+    let pools = command(host, port, "pools").unwrap_or_default();
+    let stats = command(host, port, "stats").unwrap_or_default();
+
+    let mut devices: Vec<DeviceStats> = section(&devs, "DEVS").iter().map(parse_device).collect();
+    let temperatures = stats_temperatures(&stats);
+    if devices.is_empty() && !temperatures.is_empty() {
+        devices = temperatures
+            .iter()
+            .map(|(name, temperature)| DeviceStats {
+                name: name.clone(),
+                temperature: Some(*temperature),
+                ..DeviceStats::default()
+            })
+            .collect();
+    }
+
+    let pools: Vec<PoolStatus> = section(&pools, "POOLS").iter().map(parse_pool).collect();
+    let active_pool = pools
+        .iter()
+        .find(|pool| pool.active)
+        .or(pools.first())
+        .map(|pool| pool.url.clone());
+
+    Ok(MinerStats {
+        hashrate: Hashrate {
+            short: hashrate_field(&summary, "5s"),
+            medium: hashrate_field(&summary, "1m").or(hashrate_field(&summary, "5m")),
+            long: hashrate_field(&summary, "av"),
+        },
+        accepted_shares: summary.get("Accepted").and_then(Value::as_u64),
+        rejected_shares: summary.get("Rejected").and_then(Value::as_u64),
+        stale_shares: summary.get("Stale").and_then(Value::as_u64),
+        hardware_errors: summary.get("Hardware Errors").and_then(Value::as_u64),
+        pool: active_pool,
+        difficulty: summary
+            .get("Last Share Difficulty")
+            .and_then(Value::as_f64)
+            .map(|difficulty| difficulty as u64),
+        version: None,
+        devices,
+        pools,
+        updated_at: Utc::now().timestamp(),
+    })
+}
+
+/// Sends one command and reads the reply until the miner closes the connection.
+/// Replies are terminated by a NUL byte, and some bmminer builds emit `}{`
+/// between objects in the `stats` reply, which is patched before parsing.
+fn command(host: &str, port: u16, command: &str) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    let address = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or("address not resolved")?;
+    let mut stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    stream.write_all(format!("{{\"command\":\"{}\"}}", command).as_bytes())?;
+
+    let mut response = vec![];
+    stream.read_to_end(&mut response)?;
+    while response.last() == Some(&0) {
+        response.pop();
+    }
+
+    let response = String::from_utf8_lossy(&response).replace("}{", "},{");
+    Ok(serde_json::from_str(&response)?)
+}
+
+fn section<'a>(response: &'a Value, name: &str) -> &'a [Value] {
+    response[name].as_array().map(Vec::as_slice).unwrap_or(&[])
+}
+
+fn first_section(response: &Value, name: &str) -> Map<String, Value> {
+    section(response, name)
+        .first()
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Reads fields like `MHS 5s` or `GHS av` in H/s, whatever unit prefix the
+/// miner reports them in.
+fn hashrate_field(object: &Map<String, Value>, suffix: &str) -> Option<f64> {
+    [("KHS", 1e3), ("MHS", 1e6), ("GHS", 1e9), ("THS", 1e12)]
+        .iter()
+        .find_map(|(prefix, multiplier)| {
+            object
+                .get(&format!("{} {}", prefix, suffix))
+                .and_then(number)
+                .map(|value| value * multiplier)
+        })
+}
+
+/// Some firmwares report numbers as strings.
+fn number(value: &Value) -> Option<f64> {
+    value
+        .as_f64()
+        .or_else(|| value.as_str().and_then(|value| value.trim().parse().ok()))
+}
+
+fn parse_device(device: &Value) -> DeviceStats {
+    let object = device.as_object().cloned().unwrap_or_default();
+    let kind = ["ASC", "GPU", "PGA"].iter().find_map(|kind| {
+        object
+            .get(*kind)
+            .and_then(Value::as_u64)
+            .map(|index| (kind, index))
+    });
+
+    let name = match (object.get("Name").and_then(Value::as_str), kind) {
+        (Some(name), Some((_, index))) => format!("{} {}", name, index),
+        (None, Some((kind, index))) => format!("{} {}", kind, index),
+        (Some(name), None) => name.to_string(),
+        (None, None) => "device".to_string(),
+    };
+
+    DeviceStats {
+        name,
+        hashrate: hashrate_field(&object, "5s").or(hashrate_field(&object, "av")),
+        hardware_errors: object.get("Hardware Errors").and_then(Value::as_u64),
+        temperature: object.get("Temperature").and_then(number),
+        status: object
+            .get("Status")
+            .and_then(Value::as_str)
+            .map(String::from),
+    }
+}
+
+fn parse_pool(pool: &Value) -> PoolStatus {
+    PoolStatus {
+        url: pool["URL"].as_str().unwrap_or_default().to_string(),
+        status: pool["Status"].as_str().unwrap_or_default().to_string(),
+        active: pool["Stratum Active"].as_bool().unwrap_or(false),
+        accepted: pool["Accepted"].as_u64(),
+        rejected: pool["Rejected"].as_u64(),
+    }
+}
+
+/// Antminer style `stats` replies carry chain temperatures as `temp1`,
+/// `temp2_1`, ... fields; unused chains report 0.
+fn stats_temperatures(stats: &Value) -> Vec<(String, f64)> {
+    let mut temperatures: Vec<(String, f64)> = section(stats, "STATS")
+        .iter()
+        .filter_map(Value::as_object)
+        .flat_map(|object| object.iter())
+        .filter(|(key, _)| key.starts_with("temp") && !key.starts_with("temp_"))
+        .filter_map(|(key, value)| number(value).map(|value| (key.clone(), value)))
+        .filter(|(_, value)| *value > 0.0)
+        .collect();
+
+    temperatures.sort_by(|a, b| a.0.cmp(&b.0));
+    temperatures
+}
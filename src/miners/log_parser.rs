@@ -0,0 +1,145 @@
+use chrono::Utc;
+use regex::{Captures, Regex};
+
+use crate::miners::MinerStats;
+
+const XMRIG_PATTERNS: [&str; 2] = [
+    r"speed 10s/60s/15m (?P<short>[\d.]+|n/a) (?P<medium>[\d.]+|n/a) (?P<long>[\d.]+|n/a) (?P<unit>[kMGTP]?H/s)",
+    r"(?:accepted|rejected) \((?P<accepted>\d+)/(?P<rejected>\d+)\)",
+];
+const TREX_PATTERNS: [&str; 1] =
+    [r"\[ OK \] (?P<accepted>\d+)/(?P<total>\d+) - (?P<medium>[\d.]+) (?P<unit>[kMGTP]?H/s)"];
+const LOLMINER_PATTERNS: [&str; 1] = [r"(?i)total:?\s+(?P<medium>[\d.]+)\s*(?P<unit>[kmgtp]?h/s)"];
+const SRBMINER_PATTERNS: [&str; 2] = [
+    r"(?i)total(?: hashrate)?:?\s+(?P<medium>[\d.]+)\s*(?P<unit>[kmgtp]?h/s)",
+    r"(?i)accepted:?\s*(?P<accepted>\d+).*rejected:?\s*(?P<rejected>\d+)",
+];
+const CPUMINER_PATTERNS: [&str; 2] = [
+    r"(?i)accepted:?\s+(?P<accepted>\d+)(?:/| of )(?P<total>\d+).*?(?P<medium>[\d.]+)\s*(?P<unit>[kmgtp]?)h(?:ash)?/s",
+    r"(?i)(?:total hash ?rate|hashrate):?\s+(?P<medium>[\d.]+)\s*(?P<unit>[kmgtp]?h/s)",
+];
+
+/// Extracts hashrate and share counts from miner output. Built-in parsers and
+/// custom patterns use the same named groups: `short`, `medium` and `long`
+/// hashrates with an optional `unit`, and `accepted` with `rejected` or `total`
+/// share counts plus an optional `stale` count.
+pub struct LogParser {
+    patterns: Vec<Regex>,
+    unit: String,
+    ansi_escape: Regex,
+}
+
+impl LogParser {
+    pub fn new(parser: &str, custom_patterns: &[String], unit: Option<&str>) -> Self {
+        let built_in: &[&str] = match parser {
+            "xmrig" => &XMRIG_PATTERNS,
+            "trex" => &TREX_PATTERNS,
+            "lolminer" => &LOLMINER_PATTERNS,
+            "srbminer" => &SRBMINER_PATTERNS,
+            "cpuminer" => &CPUMINER_PATTERNS,
+            "custom" => &[],
+            _ => {
+                log::error!("Unknown log parser {}", parser);
+                &[]
+            }
+        };
+
+        let patterns = built_in
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .chain(custom_patterns.iter().cloned())
+            .filter_map(|pattern| match Regex::new(&pattern) {
+                Ok(regex) => Some(regex),
+                Err(error) => {
+                    log::error!("Invalid log parser pattern {}. message: {}", pattern, error);
+                    None
+                }
+            })
+            .collect();
+
+        LogParser {
+            patterns,
+            unit: unit.unwrap_or("H/s").to_string(),
+            ansi_escape: Regex::new(r"\x1b\[[0-9;]*m").unwrap(),
+        }
+    }
+
+    /// Updates `stats` from the given lines and returns whether anything matched.
+    pub fn parse(&self, stats: &mut MinerStats, lines: &[String]) -> bool {
+        let mut matched = false;
+
+        for line in lines {
+            let line = self.ansi_escape.replace_all(line, "");
+            for captures in self
+                .patterns
+                .iter()
+                .filter_map(|regex| regex.captures(&line))
+            {
+                matched = true;
+                self.apply(stats, &captures);
+            }
+        }
+
+        if matched {
+            stats.updated_at = Utc::now().timestamp();
+        }
+        matched
+    }
+
+    fn apply(&self, stats: &mut MinerStats, captures: &Captures) {
+        let unit = captures
+            .name("unit")
+            .map(|unit| unit.as_str())
+            .filter(|unit| !unit.is_empty())
+            .unwrap_or(&self.unit);
+        let multiplier = unit_multiplier(unit);
+        let hashrate = |name: &str| {
+            captures
+                .name(name)
+                .and_then(|value| value.as_str().parse::<f64>().ok())
+                .map(|value| value * multiplier)
+        };
+
+        if captures.name("short").is_some() {
+            stats.hashrate.short = hashrate("short");
+        }
+        if captures.name("medium").is_some() {
+            stats.hashrate.medium = hashrate("medium");
+        }
+        if captures.name("long").is_some() {
+            stats.hashrate.long = hashrate("long");
+        }
+
+        let count = |name: &str| {
+            captures
+                .name(name)
+                .and_then(|value| value.as_str().parse::<u64>().ok())
+        };
+        if let Some(accepted) = count("accepted") {
+            stats.accepted_shares = Some(accepted);
+            if let Some(rejected) = count("rejected") {
+                stats.rejected_shares = Some(rejected);
+            } else if let Some(total) = count("total") {
+                stats.rejected_shares = Some(total.saturating_sub(accepted));
+            }
+            if let Some(stale) = count("stale") {
+                stats.stale_shares = Some(stale);
+            }
+        }
+    }
+}
+
+fn unit_multiplier(unit: &str) -> f64 {
+    match unit
+        .chars()
+        .next()
+        .map(|prefix| prefix.to_ascii_lowercase())
+    {
+        Some('k') => 1e3,
+        Some('m') => 1e6,
+        Some('g') => 1e9,
+        Some('t') => 1e12,
+        Some('p') => 1e15,
+        _ => 1.0,
+    }
+}
@@ -0,0 +1,114 @@
+pub mod cgminer;
+pub mod log_parser;
+pub mod xmrig;
+
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
+
+use crate::config::StatsSource;
+
+pub type StatsCache = Arc<Mutex<HashMap<String, MinerStats>>>;
+
+/// Stats not updated for this many monitor polls, and at least
+/// `MIN_STATS_AGE` seconds, are treated as unavailable. A hung miner or one
+/// that stopped logging would otherwise keep its last hashrate forever.
+const STALE_POLLS: i64 = 3;
+const MIN_STATS_AGE: i64 = 180;
+
+/// Hashrates in H/s averaged over the miner's short, medium and long windows
+/// (10s / 60s / 15m for xmrig).
+#[derive(Debug, Clone, Default)]
+pub struct Hashrate {
+    pub short: Option<f64>,
+    pub medium: Option<f64>,
+    pub long: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DeviceStats {
+    pub name: String,
+    pub hashrate: Option<f64>,
+    pub hardware_errors: Option<u64>,
+    pub temperature: Option<f64>,
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PoolStatus {
+    pub url: String,
+    pub status: String,
+    pub active: bool,
+    pub accepted: Option<u64>,
+    pub rejected: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MinerStats {
+    pub hashrate: Hashrate,
+    pub accepted_shares: Option<u64>,
+    pub rejected_shares: Option<u64>,
+    pub stale_shares: Option<u64>,
+    pub hardware_errors: Option<u64>,
+    pub pool: Option<String>,
+    pub difficulty: Option<u64>,
+    pub version: Option<String>,
+    pub devices: Vec<DeviceStats>,
+    pub pools: Vec<PoolStatus>,
+    pub updated_at: i64,
+}
+
+impl MinerStats {
+    pub fn is_fresh(&self, now: i64) -> bool {
+        now - self.updated_at <= max_stats_age()
+    }
+}
+
+impl Hashrate {
+    /// The most stable value available, used where only one number is shown.
+    pub fn current(&self) -> Option<f64> {
+        self.medium.or(self.short).or(self.long)
+    }
+}
+
+/// The stats of the program, `None` when there are none or they are stale.
+pub fn fresh_stats(cache: &StatsCache, program: &str) -> Option<MinerStats> {
+    let now = Utc::now().timestamp();
+    cache
+        .lock()
+        .unwrap()
+        .get(program)
+        .filter(|stats| stats.is_fresh(now))
+        .cloned()
+}
+
+fn max_stats_age() -> i64 {
+    let poll_interval: i64 = env::var("MONITOR_INTERVAL")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(30);
+    (poll_interval * STALE_POLLS).max(MIN_STATS_AGE)
+}
+
+pub fn fetch_stats(source: &StatsSource) -> Result<MinerStats, Box<dyn Error + Send + Sync>> {
+    match source {
+        StatsSource::Xmrig { url, access_token } => xmrig::fetch(url, access_token.as_deref()),
+        StatsSource::Cgminer { host, port } => cgminer::fetch(host, *port),
+        StatsSource::Log { .. } => Err("log sources are parsed from the tailed output".into()),
+    }
+}
+
+pub fn format_hashrate(hashrate: f64) -> String {
+    let units = ["H/s", "kH/s", "MH/s", "GH/s", "TH/s", "PH/s"];
+    let mut value = hashrate;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < units.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+
+    format!("{:.2} {}", value, units[unit])
+}
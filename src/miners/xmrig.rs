@@ -0,0 +1,44 @@
+use std::error::Error;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde_json::Value;
+
+use crate::miners::{Hashrate, MinerStats};
+
+pub fn fetch(
+    url: &str,
+    access_token: Option<&str>,
+) -> Result<MinerStats, Box<dyn Error + Send + Sync>> {
+    let mut request = reqwest::blocking::Client::new()
+        .get(format!("{}/1/summary", url.trim_end_matches('/')))
+        .timeout(Duration::from_secs(10));
+    if let Some(access_token) = access_token {
+        request = request.bearer_auth(access_token);
+    }
+
+    let summary: Value = request.send()?.error_for_status()?.json()?;
+    Ok(parse_summary(&summary))
+}
+
+fn parse_summary(summary: &Value) -> MinerStats {
+    let total = &summary["hashrate"]["total"];
+    let connection = &summary["connection"];
+
+    MinerStats {
+        hashrate: Hashrate {
+            short: total[0].as_f64(),
+            medium: total[1].as_f64(),
+            long: total[2].as_f64(),
+        },
+        accepted_shares: connection["accepted"].as_u64(),
+        rejected_shares: connection["rejected"].as_u64(),
+        pool: connection["pool"].as_str().map(String::from),
+        difficulty: connection["diff"]
+            .as_u64()
+            .or(summary["results"]["diff_current"].as_u64()),
+        version: summary["version"].as_str().map(String::from),
+        updated_at: Utc::now().timestamp(),
+        ..MinerStats::default()
+    }
+}
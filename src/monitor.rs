@@ -0,0 +1,568 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::Utc;
+
+use crate::{
+    alert::{AlertService, Severity},
+    config::{Config, LogAction, LogStream, StatsSource},
+    history::{self, StateHistory, StateTransition},
+    log_tail::LogTailer,
+    log_watcher::{LogMatch, LogWatcher},
+    miners::{self, log_parser::LogParser, StatsCache},
+    samples::SampleStore,
+    supervisor::{Process, SupervisorService},
+    thermal::ThermalHold,
+    utils::markdown,
+    utils::timedate,
+};
+
+pub struct MonitorService {
+    supervisor_service: SupervisorService,
+    alert_service: AlertService,
+    history: Arc<Mutex<StateHistory>>,
+    stats: StatsCache,
+    samples: Arc<Mutex<SampleStore>>,
+    thermal_hold: ThermalHold,
+    config: Config,
+    interval: Duration,
+    sample_interval: i64,
+    last_sample: i64,
+    down_timeout: i64,
+    unreachable_since: Option<i64>,
+    down_alerted: bool,
+    log_tailer: Arc<Mutex<LogTailer>>,
+    log_watcher: LogWatcher,
+    log_parsers: HashMap<String, (LogParser, LogStream)>,
+    high_reject_rate: HashSet<String>,
+    low_hashrate_since: HashMap<String, i64>,
+    low_hashrate_alerted: HashSet<String>,
+    first_poll: bool,
+}
+
+impl MonitorService {
+    pub fn new(
+        history: Arc<Mutex<StateHistory>>,
+        stats: StatsCache,
+        samples: Arc<Mutex<SampleStore>>,
+        alert_service: AlertService,
+        config: &Config,
+        thermal_hold: ThermalHold,
+    ) -> Self {
+        let interval = env::var("MONITOR_INTERVAL")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(30);
+
+        let down_timeout = env::var("SUPERVISOR_DOWN_TIMEOUT")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(300);
+
+        let sample_interval = env::var("SAMPLE_INTERVAL")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(300);
+
+        MonitorService {
+            supervisor_service: SupervisorService::new(),
+            alert_service,
+            history,
+            stats,
+            samples,
+            thermal_hold,
+            interval: Duration::from_secs(interval),
+            sample_interval,
+            last_sample: 0,
+            down_timeout,
+            unreachable_since: None,
+            down_alerted: false,
+            log_tailer: Arc::new(Mutex::new(LogTailer::new())),
+            log_watcher: LogWatcher::new(config),
+            log_parsers: log_parsers(config),
+            high_reject_rate: HashSet::new(),
+            low_hashrate_since: HashMap::new(),
+            low_hashrate_alerted: HashSet::new(),
+            first_poll: true,
+            config: config.clone(),
+        }
+    }
+
+    pub async fn run(&mut self) {
+        loop {
+            self.poll().await;
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    async fn poll(&mut self) {
+        let supervisor_service = self.supervisor_service.clone();
+        let result =
+            tokio::task::spawn_blocking(move || supervisor_service.try_process_list()).await;
+        let now = Utc::now().timestamp();
+
+        let process_list = match result {
+            Ok(Ok(process_list)) => process_list,
+            Ok(Err(error)) => {
+                log::error!("Error in poll supervisor programs. message: {}", error);
+                self.history.lock().unwrap().mark_unknown();
+                self.supervisor_unreachable(now).await;
+                return;
+            }
+            Err(error) => {
+                log::error!("Error in poll supervisor programs. message: {}", error);
+                return;
+            }
+        };
+
+        if let Some(since) = self.unreachable_since.take() {
+            if self.down_alerted {
+                self.alert_service
+                    .send(
+                        Severity::Info,
+                        format!(
+                            "✅ Supervisor is reachable again after {}\\.",
+                            markdown::replace_specail_chars(&timedate::format_duration(
+                                now - since
+                            ))
+                        ),
+                    )
+                    .await;
+            }
+            self.down_alerted = false;
+        }
+
+        let transitions = self.history.lock().unwrap().record(&process_list, now);
+
+        for transition in transitions {
+            log::info!(
+                "Program {} changed state to {}{}",
+                transition.program,
+                transition.state,
+                if transition.crash { " (crash)" } else { "" }
+            );
+
+            // The first poll only reports the states found at startup, the bot
+            // already reports the restarts it does itself, and an unknown
+            // period isn't a state the program was in.
+            if !self.first_poll && !transition.intentional && transition.state != history::UNKNOWN {
+                self.alert_state_change(&transition).await;
+            }
+        }
+        self.first_poll = false;
+
+        self.process_logs(&process_list, now).await;
+        self.update_stats(&process_list).await;
+        self.drop_stale_stats(now);
+        self.check_hashrates(&process_list, now).await;
+
+        if now - self.last_sample >= self.sample_interval {
+            self.last_sample = now;
+            {
+                let stats = self.stats.lock().unwrap();
+                self.samples
+                    .lock()
+                    .unwrap()
+                    .record(&process_list, &stats, now);
+            }
+
+            self.check_reject_rates(now).await;
+        }
+    }
+
+    async fn check_hashrates(&mut self, process_list: &[Process], now: i64) {
+        for process in process_list {
+            let low_hashrate = match self.config.programs.get(&process.name) {
+                Some(program_config) => match &program_config.low_hashrate {
+                    Some(low_hashrate) => low_hashrate.clone(),
+                    None => continue,
+                },
+                None => continue,
+            };
+
+            if process.state != "RUNNING" {
+                self.low_hashrate_since.remove(&process.name);
+                self.low_hashrate_alerted.remove(&process.name);
+                continue;
+            }
+
+            let hashrate = match miners::fresh_stats(&self.stats, &process.name)
+                .and_then(|stats| stats.hashrate.current())
+            {
+                Some(hashrate) => hashrate,
+                None => continue,
+            };
+
+            let threshold = low_hashrate.expected * (1.0 - low_hashrate.tolerance / 100.0);
+            if hashrate >= threshold {
+                self.low_hashrate_since.remove(&process.name);
+                if self.low_hashrate_alerted.remove(&process.name) {
+                    self.alert_service
+                        .send(
+                            Severity::Info,
+                            format!(
+                                "✅ Hashrate of *{}* is back to {}\\.",
+                                markdown::replace_specail_chars(&process.name),
+                                markdown::replace_specail_chars(&miners::format_hashrate(hashrate))
+                            ),
+                        )
+                        .await;
+                }
+                continue;
+            }
+
+            let since = *self
+                .low_hashrate_since
+                .entry(process.name.clone())
+                .or_insert(now);
+            if now - since < low_hashrate.duration * 60
+                || self.low_hashrate_alerted.contains(&process.name)
+            {
+                continue;
+            }
+            self.low_hashrate_alerted.insert(process.name.clone());
+
+            log::warn!(
+                "Hashrate of {} is {} H/s, below {} H/s since {}",
+                process.name,
+                hashrate,
+                threshold,
+                since
+            );
+
+            let mut text = format!(
+                "📉 Hashrate of *{}* has been below {} for {}: {} instead of {}\\.",
+                markdown::replace_specail_chars(&process.name),
+                markdown::replace_specail_chars(&miners::format_hashrate(threshold)),
+                markdown::replace_specail_chars(&timedate::format_duration(now - since)),
+                markdown::replace_specail_chars(&miners::format_hashrate(hashrate)),
+                markdown::replace_specail_chars(&miners::format_hashrate(low_hashrate.expected))
+            );
+
+            if low_hashrate.restart && self.is_held(&process.name) {
+                text.push_str("\nAutomatic restart: skipped, stopped for overheating");
+            } else if low_hashrate.restart {
+                self.history
+                    .lock()
+                    .unwrap()
+                    .expect_restart(&process.name, now);
+                let supervisor_service = self.supervisor_service.clone();
+                let program = process.name.clone();
+                let restarted = tokio::task::spawn_blocking(move || {
+                    supervisor_service.restart_process(program)
+                })
+                .await
+                .unwrap_or(false);
+
+                log::info!(
+                    "Automatic restart of {} after low hashrate: {}",
+                    process.name,
+                    if restarted { "done" } else { "failed" }
+                );
+
+                // Stats from before the restart would count against the new run.
+                self.stats.lock().unwrap().remove(&process.name);
+                self.low_hashrate_since.remove(&process.name);
+
+                text.push_str(&format!(
+                    "\nAutomatic restart: {}",
+                    if restarted { "done ✅" } else { "failed ❌" }
+                ));
+            }
+
+            self.alert_service.send(Severity::Warning, text).await;
+        }
+    }
+
+    async fn check_reject_rates(&mut self, now: i64) {
+        let mut alerts = vec![];
+
+        for (program, program_config) in &self.config.programs {
+            let reject_rate = match &program_config.reject_rate {
+                Some(reject_rate) => reject_rate,
+                None => continue,
+            };
+
+            let counts = self.samples.lock().unwrap().share_counts(
+                program,
+                now - reject_rate.window * 60,
+                now,
+            );
+            let rate = match counts {
+                Some(counts) if counts.total() >= reject_rate.min_shares => {
+                    counts.reject_rate().unwrap_or_default()
+                }
+                _ => continue,
+            };
+
+            if rate > reject_rate.threshold && self.high_reject_rate.insert(program.clone()) {
+                alerts.push((
+                    Severity::Warning,
+                    format!(
+                        "⚠️ Reject rate of *{}* is {} over the last {} minutes \\(threshold {}\\)\\.",
+                        markdown::replace_specail_chars(program),
+                        markdown::replace_specail_chars(&format!("{:.1}%", rate)),
+                        reject_rate.window,
+                        markdown::replace_specail_chars(&format!("{:.1}%", reject_rate.threshold))
+                    ),
+                ));
+            } else if rate <= reject_rate.threshold && self.high_reject_rate.remove(program) {
+                alerts.push((
+                    Severity::Info,
+                    format!(
+                        "✅ Reject rate of *{}* is back to {}\\.",
+                        markdown::replace_specail_chars(program),
+                        markdown::replace_specail_chars(&format!("{:.1}%", rate))
+                    ),
+                ));
+            }
+        }
+
+        for (severity, text) in alerts {
+            self.alert_service.send(severity, text).await;
+        }
+    }
+
+    async fn update_stats(&self, process_list: &[Process]) {
+        for process in process_list {
+            let source = match self.config.programs.get(&process.name) {
+                Some(program_config) => match &program_config.stats {
+                    Some(source) => source.clone(),
+                    None => continue,
+                },
+                None => continue,
+            };
+
+            if process.state != "RUNNING" {
+                self.stats.lock().unwrap().remove(&process.name);
+                continue;
+            }
+            if let StatsSource::Log { .. } = source {
+                continue;
+            }
+
+            let result = tokio::task::spawn_blocking(move || miners::fetch_stats(&source))
+                .await
+                .unwrap();
+
+            match result {
+                Ok(stats) => {
+                    self.stats
+                        .lock()
+                        .unwrap()
+                        .insert(process.name.clone(), stats);
+                }
+                Err(error) => {
+                    log::error!(
+                        "Error in fetch stats of {}. message: {}",
+                        process.name,
+                        error
+                    );
+                    self.stats.lock().unwrap().remove(&process.name);
+                }
+            }
+        }
+    }
+
+    /// Forgets stats that weren't updated for a while, so a miner that hung or
+    /// stopped logging shows no hashrate instead of its last one.
+    fn drop_stale_stats(&self, now: i64) {
+        self.stats.lock().unwrap().retain(|program, stats| {
+            let fresh = stats.is_fresh(now);
+            if !fresh {
+                log::warn!(
+                    "Stats of {} weren't updated since {}, dropping them",
+                    program,
+                    stats.updated_at
+                );
+            }
+            fresh
+        });
+    }
+
+    /// Tails every log stream needed by the log rules or the log parser of a
+    /// program once, and feeds the new lines to both.
+    async fn process_logs(&mut self, process_list: &[Process], now: i64) {
+        for process in process_list {
+            let parser = self.log_parsers.get(&process.name);
+            let mut streams = self.log_watcher.streams(&process.name);
+            if let Some((_, parser_stream)) = parser {
+                for stream in [LogStream::Stdout, LogStream::Stderr] {
+                    if parser_stream.covers(stream) && !streams.contains(&stream) {
+                        streams.push(stream);
+                    }
+                }
+            }
+
+            for stream in streams {
+                let lines = self.tail_log(process, stream).await;
+                if lines.is_empty() {
+                    continue;
+                }
+
+                if let Some((parser, parser_stream)) = self.log_parsers.get(&process.name) {
+                    if process.state == "RUNNING" && parser_stream.covers(stream) {
+                        let mut stats = self.stats.lock().unwrap();
+                        let mut program_stats =
+                            stats.get(&process.name).cloned().unwrap_or_default();
+                        if parser.parse(&mut program_stats, &lines) {
+                            stats.insert(process.name.clone(), program_stats);
+                        }
+                    }
+                }
+
+                let log_matches = self.log_watcher.check(&process.name, stream, &lines, now);
+
+                for log_match in log_matches {
+                    self.handle_log_match(log_match).await;
+                }
+            }
+        }
+    }
+
+    async fn tail_log(&self, process: &Process, stream: LogStream) -> Vec<String> {
+        let supervisor_service = self.supervisor_service.clone();
+        let log_tailer = self.log_tailer.clone();
+        let process = process.clone();
+
+        tokio::task::spawn_blocking(move || {
+            log_tailer
+                .lock()
+                .unwrap()
+                .new_lines(&supervisor_service, &process, stream)
+        })
+        .await
+        .unwrap_or_default()
+    }
+
+    async fn handle_log_match(&self, log_match: LogMatch) {
+        log::warn!(
+            "Log of {} matched {}: {}",
+            log_match.program,
+            log_match.rule.pattern,
+            log_match.line
+        );
+
+        let mut text = format!(
+            "{} Log of *{}* matched `{}`:\n```\n{}\n```",
+            log_match.rule.severity.emoji(),
+            markdown::replace_specail_chars(&log_match.program),
+            markdown::escape_code(&log_match.rule.pattern),
+            markdown::escape_code(&log_match.line)
+        );
+
+        if log_match.suppressed > 0 {
+            text.push_str(&format!(
+                "\n\\+{} more matches during cooldown",
+                log_match.suppressed
+            ));
+        }
+
+        let action = log_match.rule.action;
+        if action == Some(LogAction::Restart) && self.is_held(&log_match.program) {
+            text.push_str("\nAutomatic restart: skipped, stopped for overheating");
+        } else if let Some(action) = action {
+            self.history
+                .lock()
+                .unwrap()
+                .expect_restart(&log_match.program, Utc::now().timestamp());
+            let supervisor_service = self.supervisor_service.clone();
+            let program = log_match.program.clone();
+            let action_result = tokio::task::spawn_blocking(move || match action {
+                LogAction::Restart => supervisor_service.restart_process(program),
+                LogAction::Stop => supervisor_service.stop_process(program),
+            })
+            .await
+            .unwrap_or(false);
+
+            log::info!(
+                "Automatic {} of {} after log match: {}",
+                action.name(),
+                log_match.program,
+                if action_result { "done" } else { "failed" }
+            );
+
+            text.push_str(&format!(
+                "\nAutomatic {}: {}",
+                action.name(),
+                if action_result {
+                    "done ✅"
+                } else {
+                    "failed ❌"
+                }
+            ));
+        }
+
+        self.alert_service.send(log_match.rule.severity, text).await;
+    }
+
+    /// Whether the program was stopped for overheating and must stay stopped.
+    fn is_held(&self, program: &str) -> bool {
+        self.thermal_hold.lock().unwrap().contains(program)
+    }
+
+    async fn alert_state_change(&self, transition: &StateTransition) {
+        let (severity, state_emoji) = if transition.crash {
+            (Severity::Warning, "💥")
+        } else if transition.state == "RUNNING" {
+            (Severity::Info, "✅")
+        } else {
+            (Severity::Info, "❌")
+        };
+
+        self.alert_service
+            .send(
+                severity,
+                format!(
+                    "{} *{}* is now *{}*{}\\.",
+                    state_emoji,
+                    markdown::replace_specail_chars(&transition.program),
+                    &transition.state,
+                    if transition.crash {
+                        " after a crash"
+                    } else {
+                        ""
+                    }
+                ),
+            )
+            .await;
+    }
+
+    async fn supervisor_unreachable(&mut self, now: i64) {
+        let since = *self.unreachable_since.get_or_insert(now);
+
+        if !self.down_alerted && now - since >= self.down_timeout {
+            self.down_alerted = true;
+            self.alert_service
+                .send(
+                    Severity::Critical,
+                    format!(
+                        "🚨 Supervisor became unreachable {}\\.",
+                        markdown::replace_specail_chars(&timedate::diff_for_humans(since, now))
+                    ),
+                )
+                .await;
+        }
+    }
+}
+
+fn log_parsers(config: &Config) -> HashMap<String, (LogParser, LogStream)> {
+    config
+        .programs
+        .iter()
+        .filter_map(|(program, program_config)| match &program_config.stats {
+            Some(StatsSource::Log {
+                parser,
+                patterns,
+                unit,
+                stream,
+            }) => Some((
+                program.clone(),
+                (LogParser::new(parser, patterns, unit.as_deref()), *stream),
+            )),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::config::{Config, PlugKind, SmartPlug};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlugAction {
+    Off,
+    On,
+    Cycle,
+}
+
+impl PlugAction {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" => Some(PlugAction::Off),
+            "on" => Some(PlugAction::On),
+            "cycle" => Some(PlugAction::Cycle),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PlugAction::Off => "off",
+            PlugAction::On => "on",
+            PlugAction::Cycle => "cycle",
+        }
+    }
+}
+
+/// Switches the smart plugs rigs are powered through, over the local HTTP APIs
+/// of Tasmota and Shelly. Power cycles are timed by the plug itself, so even
+/// the rig running this manager comes back on. All calls block.
+#[derive(Clone)]
+pub struct PlugService {
+    plugs: HashMap<String, SmartPlug>,
+}
+
+impl PlugService {
+    pub fn new(config: &Config) -> Self {
+        PlugService {
+            plugs: config.plugs.clone(),
+        }
+    }
+
+    pub fn rigs(&self) -> Vec<String> {
+        let mut rigs: Vec<String> = self.plugs.keys().cloned().collect();
+        rigs.sort();
+        rigs
+    }
+
+    pub fn plug(&self, rig: &str) -> Option<&SmartPlug> {
+        self.plugs.get(rig)
+    }
+
+    /// Whether the relay of the rig's plug is on.
+    pub fn state(&self, rig: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let plug = self.find(rig)?;
+        let response = match plug.kind {
+            PlugKind::Tasmota => request(plug, "cm", &[("cmnd", tasmota_power(plug))])?,
+            PlugKind::Shelly => request(plug, &format!("relay/{}", plug.channel), &[])?,
+            PlugKind::ShellyRpc => request(
+                plug,
+                "rpc/Switch.GetStatus",
+                &[("id", plug.channel.to_string())],
+            )?,
+        };
+
+        let state = match plug.kind {
+            // A plug with a single relay answers with `POWER`, others with `POWER<n>`.
+            PlugKind::Tasmota => response.as_object().and_then(|object| {
+                object
+                    .iter()
+                    .find(|(key, _)| key.starts_with("POWER"))
+                    .map(|(_, value)| value == "ON")
+            }),
+            PlugKind::Shelly => response["ison"].as_bool(),
+            PlugKind::ShellyRpc => response["output"].as_bool(),
+        };
+        state.ok_or_else(|| format!("unexpected answer from the plug: {}", response).into())
+    }
+
+    pub fn power(&self, rig: &str, action: PlugAction) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let plug = self.find(rig)?;
+        log::warn!(
+            "Powering {} rig {} through {}",
+            action.as_str(),
+            rig,
+            plug.host
+        );
+
+        match plug.kind {
+            PlugKind::Tasmota => {
+                let power = tasmota_power(plug);
+                let command = match action {
+                    PlugAction::Off => format!("{} Off", power),
+                    PlugAction::On => format!("{} On", power),
+                    // Delay is in tenths of a second, up to 3600.
+                    PlugAction::Cycle => format!(
+                        "Backlog {0} Off; Delay {1}; {0} On",
+                        power,
+                        (plug.off_time * 10).min(3600)
+                    ),
+                };
+                request(plug, "cm", &[("cmnd", command)])?;
+            }
+            PlugKind::Shelly => {
+                let turn = if action == PlugAction::On {
+                    "on"
+                } else {
+                    "off"
+                };
+                let mut query = vec![("turn", turn.to_string())];
+                if action == PlugAction::Cycle {
+                    query.push(("timer", plug.off_time.to_string()));
+                }
+                request(plug, &format!("relay/{}", plug.channel), &query)?;
+            }
+            PlugKind::ShellyRpc => {
+                let mut query = vec![
+                    ("id", plug.channel.to_string()),
+                    ("on", (action == PlugAction::On).to_string()),
+                ];
+                if action == PlugAction::Cycle {
+                    query.push(("toggle_after", plug.off_time.to_string()));
+                }
+                request(plug, "rpc/Switch.Set", &query)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn find(&self, rig: &str) -> Result<&SmartPlug, Box<dyn Error + Send + Sync>> {
+        self.plugs
+            .get(rig)
+            .ok_or_else(|| format!("no plug configured for rig {}", rig).into())
+    }
+}
+
+fn tasmota_power(plug: &SmartPlug) -> String {
+    format!("Power{}", plug.channel + 1)
+}
+
+fn request(
+    plug: &SmartPlug,
+    path: &str,
+    query: &[(&str, String)],
+) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    let mut request = reqwest::blocking::Client::new()
+        .get(format!("http://{}/{}", plug.host, path))
+        .timeout(REQUEST_TIMEOUT)
+        .query(query);
+
+    // Tasmota takes the credentials as parameters, first generation Shellies as
+    // basic auth. The RPC API only supports digest auth, which isn't handled.
+    if let Some(user) = &plug.user {
+        match plug.kind {
+            PlugKind::Tasmota => {
+                request = request.query(&[
+                    ("user", user.as_str()),
+                    ("password", plug.password.as_deref().unwrap_or_default()),
+                ]);
+            }
+            PlugKind::Shelly => request = request.basic_auth(user, plug.password.as_ref()),
+            PlugKind::ShellyRpc => {}
+        }
+    }
+
+    Ok(request.send()?.error_for_status()?.json()?)
+}
@@ -0,0 +1,244 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use std::{env, thread};
+
+use chrono::Utc;
+use serde_json::{json, Value};
+
+use crate::{
+    config::{Config, PoolProfile, PoolSwitchConfig, PoolTarget},
+    history::StateHistory,
+    supervisor::SupervisorService,
+    thermal::ThermalHold,
+};
+
+/// Points miners at another pool by rewriting their config file from a profile
+/// of the `pools` registry, restarting them and restoring the backup if the
+/// miner doesn't come back up.
+#[derive(Clone)]
+pub struct PoolSwitcher {
+    supervisor_service: SupervisorService,
+    history: Arc<Mutex<StateHistory>>,
+    thermal_hold: ThermalHold,
+    config: Config,
+    settle: Duration,
+}
+
+impl PoolSwitcher {
+    pub fn new(
+        history: Arc<Mutex<StateHistory>>,
+        thermal_hold: ThermalHold,
+        config: &Config,
+    ) -> Self {
+        let settle = env::var("POOL_SWITCH_SETTLE")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(20);
+
+        PoolSwitcher {
+            supervisor_service: SupervisorService::new(),
+            history,
+            thermal_hold,
+            config: config.clone(),
+            settle: Duration::from_secs(settle),
+        }
+    }
+
+    /// The profiles the program can be switched to.
+    pub fn profiles(&self, program: &str) -> Vec<String> {
+        let pool_switch = match self.pool_switch(program) {
+            Some(pool_switch) => pool_switch,
+            None => return vec![],
+        };
+
+        let mut profiles: Vec<String> = if pool_switch.profiles.is_empty() {
+            self.config.pools.keys().cloned().collect()
+        } else {
+            pool_switch
+                .profiles
+                .iter()
+                .filter(|profile| self.config.pools.contains_key(*profile))
+                .cloned()
+                .collect()
+        };
+        profiles.sort();
+        profiles
+    }
+
+    /// The profile whose pool the program's config file currently points at:
+    /// the one with the address of the first xmrig pool, or the one a template
+    /// renders to the current file.
+    pub fn current_profile(&self, program: &str) -> Option<String> {
+        let pool_switch = self.pool_switch(program)?;
+        let content = fs::read_to_string(pool_switch.target.path()).ok()?;
+
+        match &pool_switch.target {
+            PoolTarget::Xmrig { .. } => {
+                let miner_config: Value = serde_json::from_str(&content).ok()?;
+                let url = miner_config["pools"][0]["url"].as_str()?;
+                self.profiles(program).into_iter().find(|profile| {
+                    pool_address(&self.config.pools[profile].url) == pool_address(url)
+                })
+            }
+            PoolTarget::Template { .. } => self.profiles(program).into_iter().find(|profile| {
+                render(&pool_switch.target, &self.config.pools[profile])
+                    .is_ok_and(|rendered| rendered == content)
+            }),
+        }
+    }
+
+    /// Switches the program to the profile and restarts it. Blocks until the
+    /// program had time to settle, rolling back when it isn't RUNNING by then.
+    pub fn switch(
+        &self,
+        program: &str,
+        profile_name: &str,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let pool_switch = self
+            .pool_switch(program)
+            .ok_or_else(|| format!("no pool switching configured for {}", program))?;
+        if self.thermal_hold.lock().unwrap().contains(program) {
+            return Err(format!("{} is stopped for overheating", program).into());
+        }
+        if !self
+            .profiles(program)
+            .iter()
+            .any(|name| name == profile_name)
+        {
+            return Err(format!("unknown pool profile {}", profile_name).into());
+        }
+        let profile = &self.config.pools[profile_name];
+
+        let path = pool_switch.target.path();
+        let content = render(&pool_switch.target, profile)?;
+        let backup = backup_path(path);
+        let had_config = Path::new(path).exists();
+        if had_config {
+            fs::copy(path, &backup)?;
+        }
+        write_atomically(path, &content)?;
+
+        log::info!("Switched {} to pool profile {}", program, profile_name);
+
+        if self.restart_and_settle(program) {
+            return Ok(());
+        }
+
+        log::warn!(
+            "{} didn't reach RUNNING with pool profile {}, rolling back",
+            program,
+            profile_name
+        );
+        if had_config {
+            fs::copy(&backup, path)?;
+        } else {
+            fs::remove_file(path)?;
+        }
+        self.restart_and_settle(program);
+
+        Err(format!(
+            "{} didn't reach RUNNING with {}, the previous config was restored",
+            program, profile_name
+        )
+        .into())
+    }
+
+    fn restart_and_settle(&self, program: &str) -> bool {
+        self.history
+            .lock()
+            .unwrap()
+            .expect_restart(program, Utc::now().timestamp());
+        if !self.supervisor_service.restart_process(program.to_string()) {
+            return false;
+        }
+        thread::sleep(self.settle);
+
+        match self.supervisor_service.try_process_list() {
+            Ok(process_list) => process_list
+                .iter()
+                .any(|process| process.name == program && process.state == "RUNNING"),
+            Err(error) => {
+                log::error!("Error in check state of {}. message: {}", program, error);
+                false
+            }
+        }
+    }
+
+    fn pool_switch(&self, program: &str) -> Option<&PoolSwitchConfig> {
+        self.config
+            .programs
+            .get(program)
+            .and_then(|program_config| program_config.pool_switch.as_ref())
+    }
+}
+
+fn render(
+    target: &PoolTarget,
+    profile: &PoolProfile,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    match target {
+        PoolTarget::Xmrig { path } => {
+            let mut miner_config: Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+            let miner_config_object = miner_config
+                .as_object_mut()
+                .ok_or("the miner config isn't a JSON object")?;
+
+            // The first pool is updated in place, so its other settings and the
+            // fallback pools after it are kept.
+            let pools = miner_config_object
+                .entry("pools")
+                .or_insert_with(|| json!([]));
+            if !pools.get(0).is_some_and(Value::is_object) {
+                *pools = json!([{}]);
+            }
+            let pool = pools[0].as_object_mut().unwrap();
+
+            pool.insert("url".to_string(), json!(profile.url));
+            pool.insert("user".to_string(), json!(profile.user));
+            pool.insert("pass".to_string(), json!(profile.pass));
+            pool.insert("tls".to_string(), json!(profile.tls));
+            pool.insert("enabled".to_string(), json!(true));
+            // Keys of the profile it leaves unset must not keep the values of
+            // the previous profile.
+            for (key, value) in [
+                ("coin", &profile.coin),
+                ("algo", &profile.algo),
+                ("rig-id", &profile.rig_id),
+            ] {
+                match value {
+                    Some(value) => pool.insert(key.to_string(), json!(value)),
+                    None => pool.shift_remove(key),
+                };
+            }
+
+            Ok(serde_json::to_string_pretty(&miner_config)?)
+        }
+        PoolTarget::Template { template, .. } => Ok(fs::read_to_string(template)?
+            .replace("{url}", &profile.url)
+            .replace("{user}", &profile.user)
+            .replace("{pass}", &profile.pass)
+            .replace("{coin}", profile.coin.as_deref().unwrap_or_default())
+            .replace("{algo}", profile.algo.as_deref().unwrap_or_default())
+            .replace("{rig_id}", profile.rig_id.as_deref().unwrap_or_default())),
+    }
+}
+
+/// The host and port of a pool URL, without the scheme xmrig allows in front.
+fn pool_address(url: &str) -> String {
+    let address = url.split_once("://").map_or(url, |(_, address)| address);
+    address.trim_end_matches('/').to_lowercase()
+}
+
+fn backup_path(path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.bak", path))
+}
+
+fn write_atomically(path: &str, content: &str) -> std::io::Result<()> {
+    let temporary_path = format!("{}.tmp", path);
+    fs::write(&temporary_path, content)?;
+    fs::rename(temporary_path, path)
+}
@@ -0,0 +1,105 @@
+use crate::{
+    config::{Config, PowerConfig},
+    history::StateHistory,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct PowerUsage {
+    pub kwh: f64,
+    /// Cost in the configured currency.
+    pub cost: f64,
+}
+
+/// Works out the energy used and what it cost from the configured wattages and
+/// the time programs spent running according to the uptime history.
+#[derive(Clone)]
+pub struct PowerService {
+    config: Option<PowerConfig>,
+}
+
+impl PowerService {
+    pub fn new(config: &Config) -> Self {
+        PowerService {
+            config: config.power.clone(),
+        }
+    }
+
+    pub fn currency(&self) -> &str {
+        self.config
+            .as_ref()
+            .map(|config| config.currency.as_str())
+            .unwrap_or_default()
+    }
+
+    /// Energy the program added on top of the baseline within `[from, to]`,
+    /// `None` when it has no wattage configured.
+    pub fn usage(
+        &self,
+        history: &StateHistory,
+        program: &str,
+        from: i64,
+        to: i64,
+    ) -> Option<PowerUsage> {
+        let config = self.config.as_ref()?;
+        let watts = config.programs.get(program)?;
+        let kwh = watts * history.running_secs(program, from, to) as f64 / 3600.0 / 1000.0;
+
+        Some(PowerUsage {
+            kwh,
+            cost: kwh * config.tariff,
+        })
+    }
+
+    /// Energy of the whole rig within `[from, to]`: the baseline over the whole
+    /// window plus what every program added while running.
+    pub fn rig_usage(
+        &self,
+        history: &StateHistory,
+        programs: &[String],
+        from: i64,
+        to: i64,
+    ) -> Option<PowerUsage> {
+        let config = self.config.as_ref()?;
+        let baseline_kwh = config.baseline * (to - from) as f64 / 3600.0 / 1000.0;
+        let kwh = baseline_kwh
+            + programs
+                .iter()
+                .filter_map(|program| self.usage(history, program, from, to))
+                .map(|usage| usage.kwh)
+                .sum::<f64>();
+
+        Some(PowerUsage {
+            kwh,
+            cost: kwh * config.tariff,
+        })
+    }
+
+    /// Revenue per day minus the cost per day of the usage over `[from, to]`,
+    /// `None` when the revenue is in another currency.
+    pub fn net_per_day(
+        &self,
+        value_per_day: f64,
+        currency: &str,
+        usage: &PowerUsage,
+        from: i64,
+        to: i64,
+    ) -> Option<f64> {
+        if currency != self.currency() || to <= from {
+            return None;
+        }
+        Some(value_per_day - usage.cost * 86400.0 / (to - from) as f64)
+    }
+
+    pub fn format(&self, usage: &PowerUsage) -> String {
+        format!(
+            "{:.2} kWh ≈ {:.2} {}",
+            usage.kwh,
+            usage.cost,
+            self.currency()
+        )
+    }
+
+    pub fn format_net(&self, net_per_day: f64) -> String {
+        format!("≈ {:.2} {}/day", net_per_day, self.currency())
+    }
+}
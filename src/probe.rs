@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+
+use crate::{
+    alert::{AlertService, Severity},
+    config::{Config, PoolProbe},
+    stratum,
+    utils::markdown,
+};
+
+pub type ProbeResults = Arc<Mutex<HashMap<String, Vec<ProbeResult>>>>;
+
+pub trait Stream: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Stream for T {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    pub host: String,
+    pub port: u16,
+    pub tls: bool,
+}
+
+impl Endpoint {
+    /// Parses `host:port` with an optional scheme like `stratum+tcp://`. Schemes
+    /// containing `ssl` or `tls` imply TLS unless `tls` says otherwise.
+    pub fn parse(url: &str, tls: Option<bool>) -> Result<Self, String> {
+        let (scheme, rest) = match url.split_once("://") {
+            Some((scheme, rest)) => (scheme.to_lowercase(), rest),
+            None => (String::new(), url),
+        };
+        let address = rest.split('/').next().unwrap_or_default();
+        let (host, port) = address
+            .rsplit_once(':')
+            .ok_or_else(|| format!("no port in pool url {}", url))?;
+        let port = port
+            .parse()
+            .map_err(|_| format!("invalid port in pool url {}", url))?;
+
+        Ok(Endpoint {
+            host: host
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_string(),
+            port,
+            tls: tls.unwrap_or(scheme.contains("ssl") || scheme.contains("tls")),
+        })
+    }
+
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+/// Opens a connection to the endpoint and returns it with the time the TCP
+/// connect and TLS handshake took. Certificates aren't verified, as pools
+/// commonly use self-signed ones and only reachability matters here.
+pub fn connect(
+    endpoint: &Endpoint,
+    timeout: Duration,
+) -> Result<(Box<dyn Stream>, Duration), Box<dyn Error + Send + Sync>> {
+    let started = Instant::now();
+    let address = (endpoint.host.as_str(), endpoint.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| format!("no address for {}", endpoint.host))?;
+    let stream = TcpStream::connect_timeout(&address, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    if !endpoint.tls {
+        return Ok((Box::new(stream), started.elapsed()));
+    }
+
+    let connector = native_tls::TlsConnector::builder()
+        .danger_accept_invalid_certs(true)
+        .danger_accept_invalid_hostnames(true)
+        .build()?;
+    let stream = connector
+        .connect(&endpoint.host, stream)
+        .map_err(|error| error.to_string())?;
+    Ok((Box::new(stream), started.elapsed()))
+}
+
+#[derive(Debug, Clone)]
+pub struct ProbeResult {
+    pub url: String,
+    pub latency: Option<Duration>,
+    /// Time from login to the first job, for probes with a stratum check.
+    pub first_job: Option<Duration>,
+    pub error: Option<String>,
+    /// Failed probes in a row.
+    pub failures: u32,
+    pub checks: u32,
+    pub failed_checks: u32,
+    pub checked_at: i64,
+}
+
+impl ProbeResult {
+    pub fn is_down(&self, threshold: u32) -> bool {
+        self.failures >= threshold
+    }
+
+    pub fn format(&self) -> String {
+        let status = match (self.latency, &self.error) {
+            (Some(latency), _) => match self.first_job {
+                Some(first_job) => format!(
+                    "{} ms, first job after {} ms ✅",
+                    latency.as_millis(),
+                    first_job.as_millis()
+                ),
+                None => format!("{} ms ✅", latency.as_millis()),
+            },
+            (None, Some(error)) => format!("❌ {}", error),
+            (None, None) => "unknown".to_string(),
+        };
+        format!(
+            "{}: {} ({}/{} failed)",
+            self.url, status, self.failed_checks, self.checks
+        )
+    }
+}
+
+/// Connects to the `pool_probes` of every program on an interval, keeping the
+/// latest result per endpoint and alerting about pools that stop answering.
+pub struct ProbeService {
+    alert_service: AlertService,
+    results: ProbeResults,
+    probes: HashMap<String, Vec<PoolProbe>>,
+    interval: Duration,
+    timeout: Duration,
+    failure_threshold: u32,
+    down: Vec<(String, String)>,
+    all_down: bool,
+}
+
+impl ProbeService {
+    pub fn new(results: ProbeResults, alert_service: AlertService, config: &Config) -> Self {
+        let env_or = |key: &str, default: u64| {
+            env::var(key)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        };
+
+        let probes = config
+            .programs
+            .iter()
+            .filter(|(_, program_config)| !program_config.pool_probes.is_empty())
+            .map(|(program, program_config)| (program.clone(), program_config.pool_probes.clone()))
+            .collect();
+
+        ProbeService {
+            alert_service,
+            results,
+            probes,
+            interval: Duration::from_secs(env_or("PROBE_INTERVAL", 60)),
+            timeout: Duration::from_secs(env_or("PROBE_TIMEOUT", 5)),
+            failure_threshold: env_or("PROBE_FAILURES", 3) as u32,
+            down: vec![],
+            all_down: false,
+        }
+    }
+
+    pub async fn run(&mut self) {
+        if self.probes.is_empty() {
+            return;
+        }
+
+        loop {
+            self.probe_all().await;
+            self.check().await;
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    async fn probe_all(&self) {
+        for (program, probes) in &self.probes {
+            for probe in probes {
+                let probe_config = probe.clone();
+                let timeout = self.timeout;
+                let result = tokio::task::spawn_blocking(move || {
+                    let endpoint = Endpoint::parse(&probe_config.url, probe_config.tls)?;
+                    let (stream, latency) =
+                        connect(&endpoint, timeout).map_err(|error| error.to_string())?;
+                    let first_job = match &probe_config.stratum {
+                        Some(check) => Some(
+                            stratum::check(stream, check, timeout)
+                                .map_err(|error| error.to_string())?,
+                        ),
+                        None => None,
+                    };
+                    Ok((latency, first_job))
+                })
+                .await
+                .unwrap_or_else(|error| Err(error.to_string()));
+
+                let mut results = self.results.lock().unwrap();
+                let program_results = results.entry(program.clone()).or_default();
+                let index = match program_results
+                    .iter()
+                    .position(|result| result.url == probe.url)
+                {
+                    Some(index) => index,
+                    None => {
+                        program_results.push(ProbeResult {
+                            url: probe.url.clone(),
+                            latency: None,
+                            first_job: None,
+                            error: None,
+                            failures: 0,
+                            checks: 0,
+                            failed_checks: 0,
+                            checked_at: 0,
+                        });
+                        program_results.len() - 1
+                    }
+                };
+
+                let entry = &mut program_results[index];
+                entry.checks += 1;
+                entry.checked_at = Utc::now().timestamp();
+                match result {
+                    Ok((latency, first_job)) => {
+                        entry.latency = Some(latency);
+                        entry.first_job = first_job;
+                        entry.error = None;
+                        entry.failures = 0;
+                    }
+                    Err(error) => {
+                        log::warn!("Probe of {} for {} failed: {}", probe.url, program, error);
+                        entry.latency = None;
+                        entry.first_job = None;
+                        entry.error = Some(error);
+                        entry.failures += 1;
+                        entry.failed_checks += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn check(&mut self) {
+        let (down, reachable): (Vec<(String, String)>, usize) = {
+            let results = self.results.lock().unwrap();
+            let down = results
+                .iter()
+                .flat_map(|(program, program_results)| {
+                    program_results
+                        .iter()
+                        .filter(|result| result.is_down(self.failure_threshold))
+                        .map(|result| (program.clone(), result.url.clone()))
+                })
+                .collect();
+            let reachable = results
+                .values()
+                .flatten()
+                .filter(|result| result.latency.is_some())
+                .count();
+            (down, reachable)
+        };
+
+        let mut alerts = vec![];
+
+        if reachable == 0 && !down.is_empty() {
+            if !self.all_down {
+                alerts.push((
+                    Severity::Critical,
+                    "🌐 No pool is reachable, the rig's network seems to be down\\.".to_string(),
+                ));
+            }
+            self.all_down = true;
+        } else {
+            if self.all_down {
+                alerts.push((
+                    Severity::Info,
+                    "✅ Pools are reachable again\\.".to_string(),
+                ));
+                // Pools that were only down because of the outage recovered with it.
+                self.down.clear();
+            }
+            self.all_down = false;
+
+            for (program, url) in down.iter().filter(|pool| !self.down.contains(pool)) {
+                alerts.push((
+                    Severity::Warning,
+                    format!(
+                        "🌐 Pool {} of *{}* is unreachable while other pools are fine\\.",
+                        markdown::replace_specail_chars(url),
+                        markdown::replace_specail_chars(program)
+                    ),
+                ));
+            }
+            for (program, url) in self.down.iter().filter(|pool| !down.contains(pool)) {
+                alerts.push((
+                    Severity::Info,
+                    format!(
+                        "✅ Pool {} of *{}* is reachable again\\.",
+                        markdown::replace_specail_chars(url),
+                        markdown::replace_specail_chars(program)
+                    ),
+                ));
+            }
+        }
+        self.down = down;
+
+        for (severity, text) in alerts {
+            self.alert_service.send(severity, text).await;
+        }
+    }
+}
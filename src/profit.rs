@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::Utc;
+
+use crate::{
+    alert::{AlertService, Severity},
+    config::{Config, ProfitConfig},
+    history::StateHistory,
+    samples::SampleStore,
+    supervisor::{Process, SupervisorService},
+    thermal::ThermalHold,
+    utils::{json_source, markdown},
+};
+
+/// Hashrates are averaged over this window, so programs that aren't running
+/// are judged by how they did the last time they ran.
+const HASHRATE_WINDOW_SECS: i64 = 7 * 86400;
+
+/// Keeps the most profitable of the `profit.programs` running. Switching only
+/// happens while one of them runs, so stopping them all pauses the switcher.
+pub struct ProfitSwitcher {
+    supervisor_service: SupervisorService,
+    alert_service: AlertService,
+    samples: Arc<Mutex<SampleStore>>,
+    history: Arc<Mutex<StateHistory>>,
+    thermal_hold: ThermalHold,
+    config: Option<ProfitConfig>,
+}
+
+impl ProfitSwitcher {
+    pub fn new(
+        samples: Arc<Mutex<SampleStore>>,
+        history: Arc<Mutex<StateHistory>>,
+        alert_service: AlertService,
+        config: &Config,
+        thermal_hold: ThermalHold,
+    ) -> Self {
+        ProfitSwitcher {
+            supervisor_service: SupervisorService::new(),
+            alert_service,
+            samples,
+            history,
+            thermal_hold,
+            config: config.profit.clone(),
+        }
+    }
+
+    pub async fn run(&self) {
+        let Some(config) = &self.config else {
+            return;
+        };
+
+        loop {
+            self.check(config).await;
+            tokio::time::sleep(Duration::from_secs(config.interval as u64 * 60)).await;
+        }
+    }
+
+    async fn check(&self, config: &ProfitConfig) {
+        let source = config.source.clone();
+        let supervisor_service = self.supervisor_service.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let profitability = json_source::fetch::<HashMap<String, f64>>(&source)?;
+            let process_list = supervisor_service.try_process_list()?;
+            Ok::<_, Box<dyn Error + Send + Sync>>((profitability, process_list))
+        })
+        .await
+        .unwrap();
+
+        let (profitability, process_list) = match result {
+            Ok(result) => result,
+            Err(error) => {
+                log::error!("Error in check profitability. message: {}", error);
+                return;
+            }
+        };
+
+        let now = Utc::now().timestamp();
+        let mut revenues = self.revenues(config, &profitability, now);
+        {
+            let thermal_hold = self.thermal_hold.lock().unwrap();
+            revenues.retain(|program, _| !thermal_hold.contains(program));
+        }
+
+        let running: Vec<&Process> = process_list
+            .iter()
+            .filter(|process| {
+                config.programs.contains_key(&process.name) && process.state == "RUNNING"
+            })
+            .collect();
+        let current = match running.first() {
+            Some(current) => *current,
+            None => return,
+        };
+
+        let best = match revenues
+            .iter()
+            .max_by(|first, second| first.1.total_cmp(second.1))
+        {
+            Some((best, best_revenue)) => (best.clone(), *best_revenue),
+            None => return,
+        };
+        if best.0 == current.name {
+            return;
+        }
+
+        let current_revenue = revenues.get(&current.name).copied().unwrap_or_default();
+        if best.1 <= current_revenue * (1.0 + config.hysteresis / 100.0) {
+            return;
+        }
+        if now - current.start_time < config.min_dwell * 60 {
+            log::info!(
+                "{} is more profitable than {}, waiting for the minimum dwell time",
+                best.0,
+                current.name
+            );
+            return;
+        }
+
+        let to_stop: Vec<String> = running.iter().map(|process| process.name.clone()).collect();
+        {
+            // The switch is reported below, not as state changes.
+            let mut history = self.history.lock().unwrap();
+            for program in to_stop.iter().chain([&best.0]) {
+                history.expect_restart(program, now);
+            }
+        }
+        let supervisor_service = self.supervisor_service.clone();
+        let program = best.0.clone();
+        let fallback = current.name.clone();
+        let started = tokio::task::spawn_blocking(move || {
+            for name in to_stop {
+                supervisor_service.stop_process(name);
+            }
+            let started = supervisor_service.start_process(program);
+            if !started {
+                supervisor_service.start_process(fallback);
+            }
+            started
+        })
+        .await
+        .unwrap_or(false);
+
+        log::info!(
+            "Profit switch from {} to {}: {}",
+            current.name,
+            best.0,
+            if started { "done" } else { "failed" }
+        );
+
+        let text = if started {
+            format!(
+                "💱 Switched from *{}* to *{}*, estimated revenue per day {} instead of {}\\.",
+                markdown::replace_specail_chars(&current.name),
+                markdown::replace_specail_chars(&best.0),
+                markdown::replace_specail_chars(&format!("{:.4}", best.1)),
+                markdown::replace_specail_chars(&format!("{:.4}", current_revenue))
+            )
+        } else {
+            format!(
+                "❌ Profit switch from *{}* to *{}* failed, *{}* was started again\\.",
+                markdown::replace_specail_chars(&current.name),
+                markdown::replace_specail_chars(&best.0),
+                markdown::replace_specail_chars(&current.name)
+            )
+        };
+        let severity = if started {
+            Severity::Info
+        } else {
+            Severity::Warning
+        };
+        self.alert_service.send(severity, text).await;
+    }
+
+    /// Estimated revenue per day of every program with a known hashrate and a
+    /// profitability for its algorithm.
+    fn revenues(
+        &self,
+        config: &ProfitConfig,
+        profitability: &HashMap<String, f64>,
+        now: i64,
+    ) -> HashMap<String, f64> {
+        let samples = self.samples.lock().unwrap();
+
+        config
+            .programs
+            .iter()
+            .filter_map(|(program, profit_program)| {
+                let hashrate = samples
+                    .average_hashrate(program, now - HASHRATE_WINDOW_SECS, now)
+                    .or(profit_program.hashrate)?;
+                let per_hash = profitability.get(&profit_program.algorithm)?;
+                Some((program.clone(), hashrate * per_hash))
+            })
+            .collect()
+    }
+}
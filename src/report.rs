@@ -0,0 +1,280 @@
+use std::env;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveTime, Utc, Weekday};
+use teloxide::{
+    payloads::SendMessageSetters, requests::Requester, types::ChatId, types::ParseMode, Bot,
+};
+
+use crate::{
+    earnings::EarningsService,
+    history::StateHistory,
+    miners,
+    power::PowerService,
+    samples::SampleStore,
+    supervisor::{Process, SupervisorService},
+    utils::markdown,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFrequency {
+    Daily,
+    Weekly(Weekday),
+    Off,
+}
+
+pub struct ReportService {
+    supervisor_service: SupervisorService,
+    history: Arc<Mutex<StateHistory>>,
+    samples: Arc<Mutex<SampleStore>>,
+    earnings: EarningsService,
+    power: PowerService,
+    chat_id: ChatId,
+    time: NaiveTime,
+    frequency: ReportFrequency,
+}
+
+impl ReportService {
+    pub fn new(
+        history: Arc<Mutex<StateHistory>>,
+        samples: Arc<Mutex<SampleStore>>,
+        earnings: EarningsService,
+        power: PowerService,
+    ) -> Self {
+        let chat_id = env::var("REPORT_CHAT_ID")
+            .ok()
+            .filter(|value| !value.is_empty())
+            .unwrap_or_else(|| env::var("ADMIN_ID").unwrap())
+            .parse()
+            .unwrap();
+
+        let time = env::var("REPORT_TIME")
+            .ok()
+            .and_then(|value| NaiveTime::parse_from_str(&value, "%H:%M").ok())
+            .unwrap_or(NaiveTime::from_hms_opt(8, 0, 0).unwrap());
+
+        let frequency = match env::var("REPORT_FREQUENCY").unwrap_or_default().as_str() {
+            "off" => ReportFrequency::Off,
+            "weekly" => ReportFrequency::Weekly(
+                env::var("REPORT_WEEKDAY")
+                    .ok()
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(Weekday::Mon),
+            ),
+            _ => ReportFrequency::Daily,
+        };
+
+        ReportService {
+            supervisor_service: SupervisorService::new(),
+            history,
+            samples,
+            earnings,
+            power,
+            chat_id: ChatId(chat_id),
+            time,
+            frequency,
+        }
+    }
+
+    pub async fn build_report(&self, window: Duration) -> String {
+        let supervisor_service = self.supervisor_service.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            supervisor_service
+                .try_process_list()
+                .map_err(|error| error.to_string())
+        })
+        .await
+        .unwrap_or_else(|error| Err(error.to_string()));
+
+        let process_list = match result {
+            Ok(process_list) => process_list,
+            Err(error) => {
+                log::error!("Error in list programs for report. message: {}", error);
+                return format!(
+                    "📋 *Fleet report* \\(last {}\\)\n\n⚠️ Supervisor is unreachable, no report could be built: {}",
+                    format_period(window),
+                    markdown::replace_specail_chars(&error)
+                );
+            }
+        };
+
+        let history = self.history.lock().unwrap();
+        let samples = self.samples.lock().unwrap();
+        format_report(
+            &process_list,
+            &history,
+            &samples,
+            &self.earnings,
+            &self.power,
+            Utc::now().timestamp(),
+            window,
+        )
+    }
+
+    pub async fn run_schedule(&self, bot: &Bot) {
+        let window = match self.frequency {
+            ReportFrequency::Daily => Duration::days(1),
+            ReportFrequency::Weekly(_) => Duration::days(7),
+            ReportFrequency::Off => return,
+        };
+
+        loop {
+            let now = Local::now();
+            let next_run = self.next_run(now);
+            tokio::time::sleep((next_run - now).to_std().unwrap_or_default()).await;
+
+            let text = self.build_report(window).await;
+            if let Err(error) = bot
+                .send_message(self.chat_id, text)
+                .parse_mode(ParseMode::MarkdownV2)
+                .await
+            {
+                log::error!("Error in send scheduled report. message: {}", error);
+            }
+        }
+    }
+
+    fn next_run(&self, now: DateTime<Local>) -> DateTime<Local> {
+        let mut date = now.date_naive();
+        loop {
+            let candidate = date
+                .and_time(self.time)
+                .and_local_timezone(Local)
+                .earliest();
+
+            if let Some(candidate) = candidate {
+                let weekday_matches = match self.frequency {
+                    ReportFrequency::Weekly(weekday) => candidate.weekday() == weekday,
+                    _ => true,
+                };
+                if candidate > now && weekday_matches {
+                    return candidate;
+                }
+            }
+            date = date.succ_opt().unwrap();
+        }
+    }
+}
+
+fn format_report(
+    process_list: &[Process],
+    history: &StateHistory,
+    samples: &SampleStore,
+    earnings: &EarningsService,
+    power: &PowerService,
+    now: i64,
+    window: Duration,
+) -> String {
+    let from = now - window.num_seconds();
+
+    let programs = process_list
+        .iter()
+        .map(|program| {
+            let state_emoji = if program.state == "RUNNING" {
+                "✅"
+            } else {
+                "❌"
+            };
+
+            let uptime = match history.uptime_percent(&program.name, from, now) {
+                Some(percent) => format!("{:.1}%", percent),
+                None => "unknown".to_string(),
+            };
+
+            let hashrate = match samples.average_hashrate(&program.name, from, now) {
+                Some(hashrate) => format!(
+                    "\n*average hashrate*: {}",
+                    markdown::replace_specail_chars(&miners::format_hashrate(hashrate))
+                ),
+                None => String::new(),
+            };
+
+            let estimate = earnings.estimate(history, samples, &program.name, from, now);
+            let usage = power.usage(history, &program.name, from, now);
+            let mut money = match &estimate {
+                Some(estimate) => format!(
+                    "\n*estimated earnings*: {}",
+                    markdown::replace_specail_chars(&earnings.format(estimate))
+                ),
+                None => String::new(),
+            };
+            if let Some(usage) = &usage {
+                money += &format!(
+                    "\n*power*: {}",
+                    markdown::replace_specail_chars(&power.format(usage))
+                );
+            }
+            let net = estimate
+                .and_then(|estimate| estimate.value_per_day)
+                .zip(usage)
+                .and_then(|(value, usage)| {
+                    power.net_per_day(value, earnings.currency(), &usage, from, now)
+                });
+            if let Some(net) = net {
+                money += &format!(
+                    "\n*net*: {}",
+                    markdown::replace_specail_chars(&power.format_net(net))
+                );
+            }
+
+            format!(
+                "*name*: {}\n*status*: *{}* {}\n*uptime*: {}\n*crashes*: {}{}{}",
+                markdown::replace_specail_chars(&program.name),
+                &program.state,
+                state_emoji,
+                markdown::replace_specail_chars(&uptime),
+                history.crash_count(&program.name, from, now),
+                hashrate,
+                money
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(&markdown::replace_specail_chars(
+            "\n---------------------------------\n",
+        ));
+
+    let program_names: Vec<String> = process_list
+        .iter()
+        .map(|program| program.name.clone())
+        .collect();
+    let total_value = earnings.total_value(history, samples, &program_names, from, now);
+    let rig_usage = power.rig_usage(history, &program_names, from, now);
+    let mut total = match total_value {
+        Some(total) => format!(
+            "\n\n*rig earnings*: {}",
+            markdown::replace_specail_chars(&format!("≈ {:.2} {}/day", total, earnings.currency()))
+        ),
+        None => String::new(),
+    };
+    if let Some(usage) = &rig_usage {
+        total += &format!(
+            "{}*rig power*: {}",
+            if total.is_empty() { "\n\n" } else { "\n" },
+            markdown::replace_specail_chars(&power.format(usage))
+        );
+    }
+    let net = total_value.zip(rig_usage).and_then(|(value, usage)| {
+        power.net_per_day(value, earnings.currency(), &usage, from, now)
+    });
+    if let Some(net) = net {
+        total += &format!(
+            "\n*rig net*: {}",
+            markdown::replace_specail_chars(&power.format_net(net))
+        );
+    }
+
+    format!(
+        "📋 *Fleet report* \\(last {}\\)\n\n\n{}{}\n\n\\.",
+        format_period(window),
+        &programs,
+        total
+    )
+}
+
+fn format_period(window: Duration) -> String {
+    if window.num_days() == 1 {
+        "24h".to_string()
+    } else {
+        format!("{} days", window.num_days())
+    }
+}
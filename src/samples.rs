@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{miners::MinerStats, supervisor::Process, utils::jsonl};
+
+const RETENTION_SECS: i64 = 8 * 86400;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Sample {
+    pub program: String,
+    pub timestamp: i64,
+    pub hashrate: Option<f64>,
+    #[serde(default)]
+    pub accepted_shares: Option<u64>,
+    #[serde(default)]
+    pub rejected_shares: Option<u64>,
+    #[serde(default)]
+    pub stale_shares: Option<u64>,
+}
+
+/// Shares submitted within a window, summed from the miners' counters.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShareCounts {
+    pub accepted: u64,
+    pub rejected: u64,
+    pub stale: u64,
+}
+
+impl ShareCounts {
+    pub fn total(&self) -> u64 {
+        self.accepted + self.rejected + self.stale
+    }
+
+    /// Percentage of rejected and stale shares, `None` without any shares.
+    pub fn reject_rate(&self) -> Option<f64> {
+        if self.total() == 0 {
+            None
+        } else {
+            Some((self.rejected + self.stale) as f64 * 100.0 / self.total() as f64)
+        }
+    }
+}
+
+/// Hashrate and share counter samples of every program, persisted to `SAMPLES_FILE` so charts
+/// and averages cover the time before a restart of the bot too.
+#[derive(Debug)]
+pub struct SampleStore {
+    path: PathBuf,
+    samples: Vec<Sample>,
+}
+
+impl SampleStore {
+    pub fn new() -> Self {
+        let path = PathBuf::from(
+            env::var("SAMPLES_FILE").unwrap_or_else(|_| "data/samples.jsonl".to_string()),
+        );
+
+        SampleStore {
+            samples: jsonl::read(&path),
+            path,
+        }
+    }
+
+    /// Stores one sample per program; programs that aren't running are stored
+    /// without a hashrate so gaps show up in charts.
+    pub fn record(
+        &mut self,
+        process_list: &[Process],
+        stats: &HashMap<String, MinerStats>,
+        now: i64,
+    ) {
+        let stats: HashMap<&String, &MinerStats> = stats
+            .iter()
+            .filter(|(_, stats)| stats.is_fresh(now))
+            .collect();
+        let new_samples: Vec<Sample> = process_list
+            .iter()
+            .filter(|process| stats.contains_key(&process.name) || self.has_samples(&process.name))
+            .map(|process| {
+                let stats = stats.get(&process.name);
+                Sample {
+                    program: process.name.clone(),
+                    timestamp: now,
+                    hashrate: stats.and_then(|stats| stats.hashrate.current()),
+                    accepted_shares: stats.and_then(|stats| stats.accepted_shares),
+                    rejected_shares: stats.and_then(|stats| stats.rejected_shares),
+                    stale_shares: stats.and_then(|stats| stats.stale_shares),
+                }
+            })
+            .collect();
+
+        self.samples.extend(new_samples.iter().cloned());
+
+        // Rewriting the whole file is only worth it once an hour of samples expired.
+        let cutoff = now - RETENTION_SECS;
+        let expired = self
+            .samples
+            .first()
+            .is_some_and(|sample| sample.timestamp < cutoff - 3600);
+        if expired {
+            self.samples.retain(|sample| sample.timestamp >= cutoff);
+            jsonl::rewrite(&self.path, &self.samples);
+        } else {
+            jsonl::append(&self.path, &new_samples);
+        }
+    }
+
+    pub fn series(&self, program: &str, from: i64, to: i64) -> Vec<&Sample> {
+        self.samples
+            .iter()
+            .filter(|sample| {
+                sample.program == program && sample.timestamp >= from && sample.timestamp <= to
+            })
+            .collect()
+    }
+
+    /// Average hashrate over the samples taken while the program was hashing.
+    pub fn average_hashrate(&self, program: &str, from: i64, to: i64) -> Option<f64> {
+        let hashrates: Vec<f64> = self
+            .series(program, from, to)
+            .iter()
+            .filter_map(|sample| sample.hashrate)
+            .collect();
+
+        if hashrates.is_empty() {
+            None
+        } else {
+            Some(hashrates.iter().sum::<f64>() / hashrates.len() as f64)
+        }
+    }
+
+    /// Sums the increments of the share counters within the window. When the
+    /// accepted counter goes down it was reset by a miner restart or pool
+    /// reconnect, so the new values count as the increment.
+    pub fn share_counts(&self, program: &str, from: i64, to: i64) -> Option<ShareCounts> {
+        let counters: Vec<(u64, u64, u64)> = self
+            .series(program, from, to)
+            .iter()
+            .filter_map(
+                |sample| match (sample.accepted_shares, sample.rejected_shares) {
+                    (Some(accepted), Some(rejected)) => {
+                        Some((accepted, rejected, sample.stale_shares.unwrap_or_default()))
+                    }
+                    _ => None,
+                },
+            )
+            .collect();
+
+        if counters.len() < 2 {
+            return None;
+        }
+
+        let mut counts = ShareCounts::default();
+        for pair in counters.windows(2) {
+            let (previous, current) = (pair[0], pair[1]);
+            let reset = current.0 < previous.0;
+            if reset {
+                counts.accepted += current.0;
+                counts.rejected += current.1;
+                counts.stale += current.2;
+            } else {
+                counts.accepted += current.0 - previous.0;
+                counts.rejected += current.1.saturating_sub(previous.1);
+                counts.stale += current.2.saturating_sub(previous.2);
+            }
+        }
+
+        Some(counts)
+    }
+
+    fn has_samples(&self, program: &str) -> bool {
+        self.samples.iter().any(|sample| sample.program == program)
+    }
+}
+
+impl Default for SampleStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,248 @@
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::config::{StratumCheck, StratumProtocol};
+
+const AGENT: &str = concat!("miner_manager/", env!("CARGO_PKG_VERSION"));
+
+/// Logs in to a pool over an open connection the way a miner would and waits
+/// for the first job, returning how long that took. Works on any stream, so it
+/// can run against a local fake pool as well as a real one.
+pub fn check<S: Read + Write>(
+    stream: S,
+    check: &StratumCheck,
+    timeout: Duration,
+) -> Result<Duration, Box<dyn Error + Send + Sync>> {
+    let started = Instant::now();
+    let mut reader = BufReader::new(stream);
+
+    let requests = match check.protocol {
+        StratumProtocol::Stratum => vec![
+            json!({ "id": 1, "method": "mining.subscribe", "params": [AGENT] }),
+            json!({ "id": 2, "method": "mining.authorize", "params": [check.user, check.pass] }),
+        ],
+        StratumProtocol::Xmrig => vec![json!({
+            "id": 1,
+            "jsonrpc": "2.0",
+            "method": "login",
+            "params": { "login": check.user, "pass": check.pass, "agent": AGENT },
+        })],
+    };
+    for request in requests {
+        let stream = reader.get_mut();
+        stream.write_all(format!("{}\n", request).as_bytes())?;
+        stream.flush()?;
+    }
+
+    // Stratum pools may send a job before they answered the authorization,
+    // so both are waited for.
+    let mut authorized = false;
+    let mut job_seen = false;
+    let mut line = String::new();
+    loop {
+        let no_job = || format!("no job within {}s", timeout.as_secs());
+        if started.elapsed() > timeout {
+            return Err(no_job().into());
+        }
+
+        line.clear();
+        match reader.read_line(&mut line) {
+            Ok(0) => return Err("the pool closed the connection".into()),
+            Ok(_) => {}
+            Err(error)
+                if matches!(
+                    error.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Err(no_job().into())
+            }
+            Err(error) => return Err(error.into()),
+        }
+        let message: Value = match serde_json::from_str(line.trim()) {
+            Ok(message) => message,
+            Err(_) if line.trim().is_empty() => continue,
+            Err(error) => return Err(format!("invalid message from the pool: {}", error).into()),
+        };
+
+        if !message["error"].is_null() {
+            return Err(format!("the pool answered with an error: {}", message["error"]).into());
+        }
+
+        let has_job = match check.protocol {
+            StratumProtocol::Stratum => {
+                if message["id"] == 2 {
+                    if message["result"] != true {
+                        return Err("the pool rejected the authorization".into());
+                    }
+                    authorized = true;
+                }
+                job_seen |= message["method"] == "mining.notify";
+                authorized && job_seen
+            }
+            StratumProtocol::Xmrig => {
+                message["method"] == "job"
+                    || (message["id"] == 1 && message["result"]["job"].is_object())
+            }
+        };
+        if has_job {
+            return Ok(started.elapsed());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
+    /// Starts a fake pool on 127.0.0.1 that answers every request line with the
+    /// lines `respond` returns for it, and connects to it.
+    fn fake_pool(respond: fn(&Value) -> Vec<Value>) -> TcpStream {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else {
+                    return;
+                };
+                let request: Value = serde_json::from_str(&line).unwrap();
+                for response in respond(&request) {
+                    if writeln!(writer, "{}", response).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        let stream = TcpStream::connect(address).unwrap();
+        stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+        stream
+    }
+
+    fn stratum_check(protocol: StratumProtocol) -> StratumCheck {
+        StratumCheck {
+            protocol,
+            user: "wallet.rig1".to_string(),
+            pass: "x".to_string(),
+        }
+    }
+
+    #[test]
+    fn stratum_job_after_authorize() {
+        let stream = fake_pool(|request| match request["method"].as_str() {
+            Some("mining.subscribe") => vec![json!({
+                "id": 1,
+                "result": [[["mining.notify", "ae6812eb4cd7735a302a8a9dd95cf71f"]], "08000002", 4],
+                "error": null,
+            })],
+            Some("mining.authorize") => vec![
+                json!({ "id": 2, "result": true, "error": null }),
+                json!({ "id": null, "method": "mining.set_difficulty", "params": [2] }),
+                json!({ "id": null, "method": "mining.notify", "params": ["bf", "4d16b6f8", "01", "02", [], "00000002", "1c2ac4af", "504e86b9", false] }),
+            ],
+            _ => vec![],
+        });
+
+        assert!(check(stream, &stratum_check(StratumProtocol::Stratum), TIMEOUT).is_ok());
+    }
+
+    fn notify_before_authorize(request: &Value, authorized: bool) -> Vec<Value> {
+        match request["method"].as_str() {
+            Some("mining.subscribe") => vec![
+                json!({
+                    "id": 1,
+                    "result": [[["mining.notify", "ae6812eb4cd7735a302a8a9dd95cf71f"]], "08000002", 4],
+                    "error": null,
+                }),
+                json!({ "id": null, "method": "mining.notify", "params": ["bf", "4d16b6f8", "01", "02", [], "00000002", "1c2ac4af", "504e86b9", false] }),
+            ],
+            Some("mining.authorize") => {
+                vec![json!({ "id": 2, "result": authorized, "error": null })]
+            }
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn stratum_job_before_authorize() {
+        let stream = fake_pool(|request| notify_before_authorize(request, true));
+        assert!(check(stream, &stratum_check(StratumProtocol::Stratum), TIMEOUT).is_ok());
+
+        let stream = fake_pool(|request| notify_before_authorize(request, false));
+        let error = check(stream, &stratum_check(StratumProtocol::Stratum), TIMEOUT).unwrap_err();
+        assert!(error.to_string().contains("rejected"));
+    }
+
+    #[test]
+    fn xmrig_job_in_login_result() {
+        let stream = fake_pool(|request| match request["method"].as_str() {
+            Some("login") => vec![json!({
+                "id": 1,
+                "jsonrpc": "2.0",
+                "error": null,
+                "result": {
+                    "id": "1be0b7b6",
+                    "job": { "blob": "0707", "job_id": "q7PLUPL25UV", "target": "b88d0600" },
+                    "status": "OK",
+                },
+            })],
+            _ => vec![],
+        });
+
+        assert!(check(stream, &stratum_check(StratumProtocol::Xmrig), TIMEOUT).is_ok());
+    }
+
+    #[test]
+    fn rejected_authorization_fails() {
+        let stream = fake_pool(|request| match request["method"].as_str() {
+            Some("mining.subscribe") => {
+                vec![json!({ "id": 1, "result": [[], "08000002", 4], "error": null })]
+            }
+            Some("mining.authorize") => vec![json!({ "id": 2, "result": false, "error": null })],
+            _ => vec![],
+        });
+        let error = check(stream, &stratum_check(StratumProtocol::Stratum), TIMEOUT).unwrap_err();
+        assert!(error.to_string().contains("rejected"), "{}", error);
+
+        let stream = fake_pool(|request| match request["method"].as_str() {
+            Some("mining.subscribe") => {
+                vec![json!({ "id": 1, "result": [[], "08000002", 4], "error": null })]
+            }
+            Some("mining.authorize") => {
+                vec![json!({ "id": 2, "result": null, "error": [24, "Unauthorized worker", null] })]
+            }
+            _ => vec![],
+        });
+        let error = check(stream, &stratum_check(StratumProtocol::Stratum), TIMEOUT).unwrap_err();
+        assert!(
+            error.to_string().contains("Unauthorized worker"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn pool_without_job_times_out() {
+        let stream = fake_pool(|request| match request["method"].as_str() {
+            Some("mining.subscribe") => {
+                vec![json!({ "id": 1, "result": [[], "08000002", 4], "error": null })]
+            }
+            Some("mining.authorize") => vec![json!({ "id": 2, "result": true, "error": null })],
+            _ => vec![],
+        });
+
+        let error = check(stream, &stratum_check(StratumProtocol::Stratum), TIMEOUT).unwrap_err();
+        assert_eq!(error.to_string(), "no job within 1s");
+    }
+}
@@ -1,15 +1,28 @@
-use crate::utils::timedate;
+use crate::{config::LogStream, utils::timedate};
 use serde::{Deserialize, Serialize};
 use std::cmp::max;
 use xmlrpc::Request;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Process {
     pub name: String,
     pub state: String,
     pub process_name: String,
     pub pid: i32,
     pub uptime: String,
+    pub start_time: i64,
+    pub stop_time: i64,
+}
+
+impl Process {
+    /// The `group:name` form supervisord expects for a single process.
+    pub fn full_name(&self) -> String {
+        if self.name == self.process_name {
+            self.name.clone()
+        } else {
+            format!("{}:{}", self.name, self.process_name)
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -17,6 +30,12 @@ pub struct SupervisorService {
     server_url: String,
 }
 
+impl Default for SupervisorService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SupervisorService {
     pub fn new() -> Self {
         SupervisorService {
@@ -25,8 +44,12 @@ impl SupervisorService {
     }
 
     pub fn process_list(&self) -> Vec<Process> {
+        self.try_process_list().unwrap()
+    }
+
+    pub fn try_process_list(&self) -> Result<Vec<Process>, xmlrpc::Error> {
         let request = Request::new("supervisor.getAllProcessInfo");
-        let response = request.call_url(&self.server_url).unwrap();
+        let response = request.call_url(&self.server_url)?;
 
         let response: Vec<Process> = response
             .as_array()
@@ -54,12 +77,15 @@ impl SupervisorService {
                     process_name: value.get("name").unwrap().as_str().unwrap().to_string(),
                     pid: value.get("pid").unwrap().as_i32().unwrap(),
                     uptime,
+                    start_time,
+                    stop_time,
                 }
             })
             .collect();
 
-        response
+        Ok(response)
     }
+
     pub fn start_process(&self, process_name: String) -> bool {
         let request = Request::new("supervisor.startProcessGroup").arg(process_name.clone());
         let response = request.call_url(&self.server_url);
@@ -70,9 +96,9 @@ impl SupervisorService {
                 log::error!(
                     "Error in start process {}. message: {}",
                     process_name,
-                    error.to_string()
+                    error
                 );
-                return false;
+                false
             }
         }
     }
@@ -84,15 +110,21 @@ impl SupervisorService {
         match response {
             Ok(_) => true,
             Err(error) => {
-                log::error!(
-                    "Error in start all process's. message: {}",
-                    error.to_string()
-                );
-                return false;
+                log::error!("Error in start all process's. message: {}", error);
+                false
             }
         }
     }
 
+    /// Starts the programs one by one, returning whether all of them started.
+    pub fn start_processes(&self, process_names: Vec<String>) -> bool {
+        let mut all_started = true;
+        for process_name in process_names {
+            all_started &= self.start_process(process_name);
+        }
+        all_started
+    }
+
     pub fn stop_process(&self, process_name: String) -> bool {
         let request = Request::new("supervisor.stopProcessGroup").arg(process_name.clone());
         let response = request.call_url(&self.server_url);
@@ -100,12 +132,8 @@ impl SupervisorService {
         match response {
             Ok(_) => true,
             Err(error) => {
-                log::error!(
-                    "Error in stop process {}. message: {}",
-                    process_name,
-                    error.to_string()
-                );
-                return false;
+                log::error!("Error in stop process {}. message: {}", process_name, error);
+                false
             }
         }
     }
@@ -114,14 +142,62 @@ impl SupervisorService {
         let request = Request::new("supervisor.stopAllProcesses");
         let response = request.call_url(&self.server_url);
 
+        match response {
+            Ok(_) => true,
+            Err(error) => {
+                log::error!("Error in stop all process's. message: {}", error);
+                false
+            }
+        }
+    }
+
+    pub fn restart_process(&self, process_name: String) -> bool {
+        self.stop_process(process_name.clone());
+        self.start_process(process_name)
+    }
+
+    /// Reads up to `length` bytes of the program's log ending at its current size.
+    /// Returns the data, the new log size to pass as the next `offset` and whether
+    /// more data was written than could be returned.
+    pub fn tail_process_log(
+        &self,
+        process: &Process,
+        stream: LogStream,
+        offset: i64,
+        length: i64,
+    ) -> Result<(String, i64, bool), xmlrpc::Error> {
+        let method = match stream {
+            LogStream::Stderr => "supervisor.tailProcessStderrLog",
+            _ => "supervisor.tailProcessStdoutLog",
+        };
+        let request = Request::new(method)
+            .arg(process.full_name())
+            .arg(offset as i32)
+            .arg(length as i32);
+        let response = request.call_url(&self.server_url)?;
+        let values = response.as_array().unwrap();
+
+        Ok((
+            values[0].as_str().unwrap_or_default().to_string(),
+            values[1].as_i64().unwrap_or_default(),
+            values[2].as_bool().unwrap_or_default(),
+        ))
+    }
+
+    /// Empties the stdout and stderr logs of a single process.
+    pub fn clear_process_logs(&self, process: &Process) -> bool {
+        let request = Request::new("supervisor.clearProcessLogs").arg(process.full_name());
+        let response = request.call_url(&self.server_url);
+
         match response {
             Ok(_) => true,
             Err(error) => {
                 log::error!(
-                    "Error in stop all process's. message: {}",
-                    error.to_string()
+                    "Error in clear logs of {}. message: {}",
+                    process.full_name(),
+                    error
                 );
-                return false;
+                false
             }
         }
     }
@@ -133,8 +209,8 @@ impl SupervisorService {
         match response {
             Ok(_) => true,
             Err(error) => {
-                log::error!("Error in reload supervisor. message: {}", error.to_string());
-                return false;
+                log::error!("Error in reload supervisor. message: {}", error);
+                false
             }
         }
     }
@@ -0,0 +1,235 @@
+use std::collections::HashSet;
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::Utc;
+
+use crate::{
+    alert::{AlertService, Severity},
+    config::Config,
+    history::StateHistory,
+    host,
+    supervisor::SupervisorService,
+    utils::markdown,
+};
+
+/// The `thermal_stop` programs while the rig is overheated. Nothing else may
+/// start or restart them until it cooled down.
+pub type ThermalHold = Arc<Mutex<HashSet<String>>>;
+
+/// Watches the rig's temperature, stopping the `thermal_stop` programs when it
+/// gets critical and starting them again once it stayed below `THERMAL_RESUME`
+/// for `THERMAL_RESUME_AFTER` minutes.
+pub struct ThermalService {
+    supervisor_service: SupervisorService,
+    alert_service: AlertService,
+    history: Arc<Mutex<StateHistory>>,
+    interval: Duration,
+    warning: f64,
+    critical: f64,
+    resume: f64,
+    resume_after: i64,
+    programs: Vec<String>,
+    hold: ThermalHold,
+    warned: bool,
+    overheated: bool,
+    stopped: Vec<String>,
+    cool_since: Option<i64>,
+}
+
+impl ThermalService {
+    pub fn new(
+        alert_service: AlertService,
+        history: Arc<Mutex<StateHistory>>,
+        config: &Config,
+        hold: ThermalHold,
+    ) -> Self {
+        let env_or = |key: &str, default: f64| {
+            env::var(key)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        };
+
+        let programs = config
+            .programs
+            .iter()
+            .filter(|(_, program_config)| program_config.thermal_stop)
+            .map(|(program, _)| program.clone())
+            .collect();
+
+        ThermalService {
+            supervisor_service: SupervisorService::new(),
+            alert_service,
+            history,
+            interval: Duration::from_secs(env_or("THERMAL_INTERVAL", 30.0) as u64),
+            warning: env_or("THERMAL_WARNING", 80.0),
+            critical: env_or("THERMAL_CRITICAL", 90.0),
+            resume: env_or("THERMAL_RESUME", 70.0),
+            resume_after: env_or("THERMAL_RESUME_AFTER", 10.0) as i64,
+            programs,
+            hold,
+            warned: false,
+            overheated: false,
+            stopped: vec![],
+            cool_since: None,
+        }
+    }
+
+    pub async fn run(&mut self) {
+        loop {
+            let temperatures = tokio::task::spawn_blocking(host::read_temperatures)
+                .await
+                .unwrap_or_default();
+
+            let hottest = temperatures
+                .into_iter()
+                .max_by(|first, second| first.1.total_cmp(&second.1));
+            if let Some((sensor, temperature)) = hottest {
+                self.check(&sensor, temperature, Utc::now().timestamp())
+                    .await;
+            }
+
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    async fn check(&mut self, sensor: &str, temperature: f64, now: i64) {
+        let reading = markdown::replace_specail_chars(&format!("{} {:.0}°C", sensor, temperature));
+
+        if temperature >= self.critical && !self.overheated {
+            self.stop_programs(&reading).await;
+            self.overheated = true;
+            self.warned = true;
+        } else if temperature >= self.warning && !self.warned {
+            self.warned = true;
+            self.alert_service
+                .send(
+                    Severity::Warning,
+                    format!("🌡 The rig is getting hot: {}\\.", reading),
+                )
+                .await;
+        } else if temperature < self.warning && self.warned && !self.overheated {
+            self.warned = false;
+            self.alert_service
+                .send(
+                    Severity::Info,
+                    format!("✅ The rig cooled down to {}\\.", reading),
+                )
+                .await;
+        }
+
+        if !self.overheated {
+            return;
+        }
+        if temperature > self.resume {
+            self.cool_since = None;
+            return;
+        }
+
+        let since = *self.cool_since.get_or_insert(now);
+        if now - since >= self.resume_after * 60 {
+            self.start_programs(&reading).await;
+        }
+    }
+
+    async fn stop_programs(&mut self, reading: &str) {
+        self.hold
+            .lock()
+            .unwrap()
+            .extend(self.programs.iter().cloned());
+        self.expect_restarts(&self.programs);
+
+        let supervisor_service = self.supervisor_service.clone();
+        let programs = self.programs.clone();
+        let stopped = if programs.is_empty() {
+            vec![]
+        } else {
+            tokio::task::spawn_blocking(move || {
+                let running: Vec<String> = match supervisor_service.try_process_list() {
+                    Ok(process_list) => process_list
+                        .into_iter()
+                        .filter(|process| {
+                            process.state == "RUNNING" && programs.contains(&process.name)
+                        })
+                        .map(|process| process.name)
+                        .collect(),
+                    Err(error) => {
+                        log::error!(
+                            "Error in list programs to stop on overheat. message: {}",
+                            error
+                        );
+                        programs
+                    }
+                };
+                running
+                    .into_iter()
+                    .filter(|program| supervisor_service.stop_process(program.clone()))
+                    .collect::<Vec<String>>()
+            })
+            .await
+            .unwrap_or_default()
+        };
+
+        log::warn!("Overheat at {}, stopped {:?}", reading, stopped);
+
+        self.alert_service
+            .send(
+                Severity::Critical,
+                format!(
+                    "🔥 The rig is overheating: {}\\. Stopped {}\\.",
+                    reading,
+                    if stopped.is_empty() {
+                        "nothing".to_string()
+                    } else {
+                        format!("*{}*", markdown::replace_specail_chars(&stopped.join(", ")))
+                    }
+                ),
+            )
+            .await;
+
+        self.stopped = stopped;
+        self.cool_since = None;
+    }
+
+    async fn start_programs(&mut self, reading: &str) {
+        let supervisor_service = self.supervisor_service.clone();
+        let programs = std::mem::take(&mut self.stopped);
+        let started = programs.join(", ");
+        self.hold.lock().unwrap().clear();
+        self.expect_restarts(&programs);
+        if !programs.is_empty() {
+            tokio::task::spawn_blocking(move || supervisor_service.start_processes(programs))
+                .await
+                .unwrap_or(false);
+        }
+
+        log::info!("Temperature back to normal, started {:?}", started);
+
+        self.warned = false;
+        self.overheated = false;
+        self.cool_since = None;
+
+        let text = if started.is_empty() {
+            format!("✅ The rig cooled down to {}\\.", reading)
+        } else {
+            format!(
+                "✅ The rig cooled down to {}, started *{}* again\\.",
+                reading,
+                markdown::replace_specail_chars(&started)
+            )
+        };
+        self.alert_service.send(Severity::Info, text).await;
+    }
+
+    /// The stops and starts are reported by the overheat alerts, not as state
+    /// changes.
+    fn expect_restarts(&self, programs: &[String]) {
+        let now = Utc::now().timestamp();
+        let mut history = self.history.lock().unwrap();
+        for program in programs {
+            history.expect_restart(program, now);
+        }
+    }
+}
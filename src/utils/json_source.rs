@@ -0,0 +1,19 @@
+use std::error::Error;
+use std::fs;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+
+/// Reads JSON from an HTTP(S) endpoint or, for anything else, a local file.
+pub fn fetch<T: DeserializeOwned>(source: &str) -> Result<T, Box<dyn Error + Send + Sync>> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        Ok(reqwest::blocking::Client::new()
+            .get(source)
+            .timeout(Duration::from_secs(10))
+            .send()?
+            .error_for_status()?
+            .json()?)
+    } else {
+        Ok(serde_json::from_str(&fs::read_to_string(source)?)?)
+    }
+}
@@ -0,0 +1,62 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+pub fn read<T: DeserializeOwned>(path: &Path) -> Vec<T> {
+    match fs::read_to_string(path) {
+        Ok(content) => content
+            .lines()
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(value) => Some(value),
+                Err(error) => {
+                    log::error!(
+                        "Error in parse line of {}. message: {}",
+                        path.display(),
+                        error
+                    );
+                    None
+                }
+            })
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+pub fn append<T: Serialize>(path: &Path, values: &[T]) {
+    if values.is_empty() {
+        return;
+    }
+
+    let result = create_parent_dir(path).and_then(|_| {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        for value in values {
+            writeln!(file, "{}", serde_json::to_string(value)?)?;
+        }
+        Ok(())
+    });
+
+    if let Err(error) = result {
+        log::error!("Error in write {}. message: {}", path.display(), error);
+    }
+}
+
+pub fn rewrite<T: Serialize>(path: &Path, values: &[T]) {
+    let content: String = values
+        .iter()
+        .filter_map(|value| serde_json::to_string(value).ok())
+        .map(|line| line + "\n")
+        .collect();
+
+    if let Err(error) = create_parent_dir(path).and_then(|_| fs::write(path, content)) {
+        log::error!("Error in write {}. message: {}", path.display(), error);
+    }
+}
+
+fn create_parent_dir(path: &Path) -> std::io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => fs::create_dir_all(parent),
+        _ => Ok(()),
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::OnceLock;
+
 use chrono::Local;
 use log::LevelFilter;
 use log4rs::{
@@ -6,12 +8,15 @@ use log4rs::{
     encode::pattern::PatternEncoder,
 };
 
+static LOG_FILE: OnceLock<String> = OnceLock::new();
+
 pub fn initilize() -> Result<(), Box<dyn std::error::Error>> {
+    let path = LOG_FILE.get_or_init(|| format!("log/{}.log", Local::now().format("%Y-%m-%d")));
     let logfile = FileAppender::builder()
         .encoder(Box::new(PatternEncoder::new(
             "[{d(%Y-%m-%d %H:%M:%S)}] - [{l}] - {m}\n",
         )))
-        .build(format!("log/{}.log", Local::now().format("%Y-%m-%d")))?;
+        .build(path)?;
 
     let config = Config::builder()
         .appender(Appender::builder().build("logfile", Box::new(logfile)))
@@ -21,3 +26,9 @@ pub fn initilize() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// The file the bot logs to, named after the day it started and kept open
+/// for as long as it runs.
+pub fn current_file() -> Option<&'static str> {
+    LOG_FILE.get().map(String::as_str)
+}
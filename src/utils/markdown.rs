@@ -1,4 +1,4 @@
-pub fn replace_specail_chars(text: &String) -> String {
+pub fn replace_specail_chars(text: &str) -> String {
     let special_chars = vec![
         '_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}', '.', '!',
     ];
@@ -13,3 +13,7 @@ pub fn replace_specail_chars(text: &String) -> String {
         })
         .collect()
 }
+
+pub fn escape_code(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('`', "\\`")
+}
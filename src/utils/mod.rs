@@ -1,3 +1,5 @@
+pub mod json_source;
+pub mod jsonl;
 pub mod logger;
 pub mod markdown;
 pub mod timedate;
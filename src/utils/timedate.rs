@@ -29,3 +29,12 @@ pub fn diff_for_humans(timestamp1: i64, timestamp2: i64) -> String {
         value, unit, plural, hours, minutes, seconds
     )
 }
+
+pub fn format_duration(seconds: i64) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}